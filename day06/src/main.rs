@@ -1,8 +1,11 @@
 use std::cmp::Ordering;
-use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::iter;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 type Point = (usize, usize);
 type Position = (Point, Dir);
@@ -15,7 +18,7 @@ enum Status {
     Loop,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Dir {
     Up,
     Right,
@@ -36,32 +39,54 @@ impl Dir {
     pub fn to_index(self, point_index: usize) -> usize {
         4 * point_index + self as usize
     }
+
+    pub fn from_glyph(c: char) -> Option<Self> {
+        match c {
+            '^' => Some(Dir::Up),
+            '>' => Some(Dir::Right),
+            'v' => Some(Dir::Down),
+            '<' => Some(Dir::Left),
+            _ => None,
+        }
+    }
+
+    pub fn glyph(self) -> char {
+        match self {
+            Dir::Up => '^',
+            Dir::Right => '>',
+            Dir::Down => 'v',
+            Dir::Left => '<',
+        }
+    }
 }
 
-fn lower_bound<T: Ord + Clone>(haystack: &[T], needle: T, temp_bound: Option<T>) -> Option<T> {
+/// Returns the largest element of `haystack` below `needle` for which `keep` holds.
+fn lower_bound<T: Ord + Copy>(haystack: &[T], needle: T, keep: impl Fn(&T) -> bool) -> Option<T> {
     let index = haystack
         .binary_search_by(|x| match x.cmp(&needle) {
             Ordering::Equal => Ordering::Greater,
             ord => ord,
         })
         .unwrap_err();
-    let result = haystack.get(index.wrapping_sub(1)).cloned();
-    temp_bound
-        .and_then(|t| (t < needle && result.clone().is_none_or(|result| t > result)).then_some(t))
-        .or(result)
+    haystack[..index].iter().rev().copied().find(keep)
 }
 
-fn upper_bound<T: Ord + Clone>(haystack: &[T], needle: T, temp_bound: Option<T>) -> Option<T> {
+/// Returns the smallest element of `haystack` above `needle` for which `keep` holds.
+fn upper_bound<T: Ord + Copy>(haystack: &[T], needle: T, keep: impl Fn(&T) -> bool) -> Option<T> {
     let index = haystack
         .binary_search_by(|x| match x.cmp(&needle) {
             Ordering::Equal => Ordering::Less,
             ord => ord,
         })
         .unwrap_err();
-    let result = haystack.get(index).cloned();
-    temp_bound
-        .and_then(|t| (t > needle && result.clone().is_none_or(|result| t < result)).then_some(t))
-        .or(result)
+    haystack[index..].iter().copied().find(keep)
+}
+
+fn min_some<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
 }
 
 enum GridState {
@@ -70,40 +95,192 @@ enum GridState {
     Point(Point),
 }
 
-struct TemporaryObstruction<'a> {
+/// Jumps straight to the next obstruction instead of walking cell by cell.
+trait Stops {
+    fn grid(&self) -> &Grid;
+
+    /// Returns the position in front of the next obstruction ahead, already turned right, or
+    /// `None` if the guard leaves the map.
+    fn stop(&self, pos: Position) -> Option<Position>;
+
+    fn stops(&self, init_pos: Position) -> impl Iterator<Item = Position> {
+        // Skip initial position.
+        iter::successors(Some(init_pos), move |&pos| self.stop(pos)).skip(1)
+    }
+}
+
+/// Obstructions added to and removed from a `Grid` without modifying it.
+struct Overlay<'a> {
     grid: &'a Grid,
-    temp_obstruction: Point,
-}
-
-impl TemporaryObstruction<'_> {
-    pub fn stops(&self, init_pos: Position) -> impl Iterator<Item = Position> + use<'_> {
-        let (t_x, t_y) = self.temp_obstruction;
-        iter::successors(Some(init_pos), move |&((x, y), dir)| {
-            let next = match dir {
-                Dir::Up => lower_bound(
-                    &self.grid.vert_obstructions[x],
-                    y,
-                    (x == t_x).then_some(t_y),
-                )
+    // The points are stored as sorted `(x, y)` pairs for lookups in columns and as sorted
+    // `(y, x)` pairs for lookups in rows, so that the jumps still use binary searches.
+    added_vert: Vec<Point>,
+    added_hor: Vec<Point>,
+    removed_vert: Vec<Point>,
+    removed_hor: Vec<Point>,
+}
+
+impl<'a> Overlay<'a> {
+    pub fn new(grid: &'a Grid) -> Self {
+        Self {
+            grid,
+            added_vert: Vec::new(),
+            added_hor: Vec::new(),
+            removed_vert: Vec::new(),
+            removed_hor: Vec::new(),
+        }
+    }
+
+    fn insert(vert: &mut Vec<Point>, hor: &mut Vec<Point>, (x, y): Point) {
+        if let Err(index) = vert.binary_search(&(x, y)) {
+            vert.insert(index, (x, y));
+        }
+        if let Err(index) = hor.binary_search(&(y, x)) {
+            hor.insert(index, (y, x));
+        }
+    }
+
+    fn delete(vert: &mut Vec<Point>, hor: &mut Vec<Point>, (x, y): Point) {
+        if let Ok(index) = vert.binary_search(&(x, y)) {
+            vert.remove(index);
+        }
+        if let Ok(index) = hor.binary_search(&(y, x)) {
+            hor.remove(index);
+        }
+    }
+
+    pub fn add(&mut self, point: Point) {
+        if self.grid.is_obstruction(point) {
+            Self::delete(&mut self.removed_vert, &mut self.removed_hor, point);
+        } else {
+            Self::insert(&mut self.added_vert, &mut self.added_hor, point);
+        }
+    }
+
+    pub fn remove(&mut self, point: Point) {
+        if self.grid.is_obstruction(point) {
+            Self::insert(&mut self.removed_vert, &mut self.removed_hor, point);
+        } else {
+            Self::delete(&mut self.added_vert, &mut self.added_hor, point);
+        }
+    }
+
+    pub fn is_obstruction(&self, (x, y): Point) -> bool {
+        if self.grid.is_obstruction((x, y)) {
+            self.removed_vert.binary_search(&(x, y)).is_err()
+        } else {
+            self.added_vert.binary_search(&(x, y)).is_ok()
+        }
+    }
+
+    pub fn step(&self, pos: Position) -> GridState {
+        let next = self.grid.neighbor(pos);
+        if !self.grid.is_in_bounds(next) {
+            GridState::OutOfBounds
+        } else if self.is_obstruction(next) {
+            GridState::Obstruction
+        } else {
+            GridState::Point(next)
+        }
+    }
+}
+
+impl Stops for Overlay<'_> {
+    fn grid(&self) -> &Grid {
+        self.grid
+    }
+
+    fn stop(&self, ((x, y), dir): Position) -> Option<Position> {
+        let grid = self.grid;
+        let keep_vert = |o_y: &usize| self.removed_vert.binary_search(&(x, *o_y)).is_err();
+        let keep_hor = |o_x: &usize| self.removed_hor.binary_search(&(y, *o_x)).is_err();
+        let in_column = |point: Option<Point>| point.filter(|p| p.0 == x).map(|p| p.1);
+        let in_row = |point: Option<Point>| point.filter(|p| p.0 == y).map(|p| p.1);
+        let next = match dir {
+            Dir::Up => lower_bound(&grid.vert_obstructions[x], y, keep_vert)
+                .max(in_column(lower_bound(&self.added_vert, (x, y), |_| true)))
                 .map(|o_y| (x, o_y + 1)),
-                Dir::Right => {
-                    upper_bound(&self.grid.hor_obstructions[y], x, (y == t_y).then_some(t_x))
-                        .map(|o_x| (o_x - 1, y))
+            Dir::Right => min_some(
+                upper_bound(&grid.hor_obstructions[y], x, keep_hor),
+                in_row(upper_bound(&self.added_hor, (y, x), |_| true)),
+            )
+            .map(|o_x| (o_x - 1, y)),
+            Dir::Down => min_some(
+                upper_bound(&grid.vert_obstructions[x], y, keep_vert),
+                in_column(upper_bound(&self.added_vert, (x, y), |_| true)),
+            )
+            .map(|o_y| (x, o_y - 1)),
+            Dir::Left => lower_bound(&grid.hor_obstructions[y], x, keep_hor)
+                .max(in_row(lower_bound(&self.added_hor, (y, x), |_| true)))
+                .map(|o_x| (o_x + 1, y)),
+        };
+        next.map(|next| (next, dir.turn_right()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Outcome {
+    /// The guard walks `steps` cells and then steps off the map.
+    Exit { steps: usize },
+    /// The guard loops every `period` moves. The first position it repeats is reached after
+    /// `steps` moves, and reached again after `steps + period` moves.
+    Loop { steps: usize, period: usize },
+}
+
+/// Answers whether a guard starting at an arbitrary position leaves the map or loops.
+struct PatrolSimulator<'a, S: Stops> {
+    stops: &'a S,
+    // The number of moves when a stop was first reached, indexed with `Dir::to_index`.
+    seen: Vec<usize>,
+    seen_indices: Vec<usize>,
+}
+
+impl<'a, S: Stops> PatrolSimulator<'a, S> {
+    pub fn new(stops: &'a S) -> Self {
+        let grid = stops.grid();
+        Self {
+            stops,
+            seen: vec![usize::MAX; grid.width * grid.height * 4],
+            seen_indices: Vec::new(),
+        }
+    }
+
+    pub fn simulate(&mut self, init_pos: Position) -> Outcome {
+        let grid = self.stops.grid();
+        let mut steps = 0;
+        let mut pos = init_pos;
+        let outcome = loop {
+            let pos_index = pos.1.to_index(grid.point_index(pos.0));
+            if self.seen[pos_index] != usize::MAX {
+                let first = self.seen[pos_index];
+                break Outcome::Loop {
+                    steps: first,
+                    period: steps - first,
+                };
+            }
+            self.seen[pos_index] = steps;
+            self.seen_indices.push(pos_index);
+            let ((x, y), dir) = pos;
+            match self.stops.stop(pos) {
+                Some(next) => {
+                    steps += x.abs_diff(next.0 .0) + y.abs_diff(next.0 .1);
+                    pos = next;
                 }
-                Dir::Down => upper_bound(
-                    &self.grid.vert_obstructions[x],
-                    y,
-                    (x == t_x).then_some(t_y),
-                )
-                .map(|o_y| (x, o_y - 1)),
-                Dir::Left => {
-                    lower_bound(&self.grid.hor_obstructions[y], x, (y == t_y).then_some(t_x))
-                        .map(|o_x| (o_x + 1, y))
+                None => {
+                    steps += match dir {
+                        Dir::Up => y,
+                        Dir::Right => grid.width - 1 - x,
+                        Dir::Down => grid.height - 1 - y,
+                        Dir::Left => x,
+                    };
+                    break Outcome::Exit { steps };
                 }
-            };
-            next.map(|next| (next, dir.turn_right()))
-        })
-        .skip(1) // Skip initial position.
+            }
+        };
+        for index in self.seen_indices.drain(..) {
+            self.seen[index] = usize::MAX;
+        }
+        outcome
     }
 }
 
@@ -136,11 +313,10 @@ impl Grid {
         }
     }
 
-    pub fn with_obstruction(&self, point: Point) -> TemporaryObstruction<'_> {
-        TemporaryObstruction {
-            grid: self,
-            temp_obstruction: point,
-        }
+    pub fn with_obstruction(&self, point: Point) -> Overlay<'_> {
+        let mut overlay = Overlay::new(self);
+        overlay.add(point);
+        overlay
     }
 
     pub fn is_in_bounds(&self, (x, y): Point) -> bool {
@@ -151,14 +327,18 @@ impl Grid {
         self.vert_obstructions[x].binary_search(&y).is_ok()
     }
 
-    pub fn step(&self, (current, dir): Position) -> GridState {
-        let (x, y) = current;
-        let next = match dir {
+    /// Returns the next point in the direction, which might be out of bounds.
+    pub fn neighbor(&self, ((x, y), dir): Position) -> Point {
+        match dir {
             Dir::Up => (x, y.wrapping_sub(1)),
             Dir::Right => (x.wrapping_add(1), y),
             Dir::Down => (x, y.wrapping_add(1)),
             Dir::Left => (x.wrapping_sub(1), y),
-        };
+        }
+    }
+
+    pub fn step(&self, pos: Position) -> GridState {
+        let next = self.neighbor(pos);
         if !self.is_in_bounds(next) {
             GridState::OutOfBounds
         } else if self.is_obstruction(next) {
@@ -173,10 +353,54 @@ impl Grid {
     }
 }
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
+#[derive(Parser)]
+struct Cli {
+    input: PathBuf,
+    /// Print the map with the patrol path and the obstructions that create loops.
+    #[arg(long)]
+    render: bool,
+    /// Print the loop created by adding an obstruction at `X,Y`.
+    #[arg(long, value_parser = parse_point)]
+    obstruction: Option<Point>,
+    /// Report whether a guard starting at `X,Y,D` leaves the map or loops, where `D` is one of
+    /// `^`, `>`, `v` and `<`.
+    #[arg(long, value_parser = parse_position)]
+    query: Vec<Position>,
+    /// Add an obstruction at `X,Y` for the queries and report whether the guards still leave.
+    #[arg(long, value_parser = parse_point)]
+    add: Vec<Point>,
+    /// Remove the obstruction at `X,Y` for the queries and report whether the guards still
+    /// leave.
+    #[arg(long, value_parser = parse_point)]
+    remove: Vec<Point>,
+    /// Cross-check the obstructions found for part 2 against a naive simulation of every
+    /// possible obstruction.
+    #[arg(long)]
+    verify: bool,
+}
+
+fn parse_point(s: &str) -> Result<Point, String> {
+    let (x, y) = s.split_once(',').ok_or("expected X,Y")?;
+    let x = x.parse().map_err(|e| format!("{e}"))?;
+    let y = y.parse().map_err(|e| format!("{e}"))?;
+    Ok((x, y))
+}
+
+fn parse_position(s: &str) -> Result<Position, String> {
+    let (point, dir) = s.rsplit_once(',').ok_or("expected X,Y,D")?;
+    let mut chars = dir.chars();
+    let dir = chars
+        .next()
+        .filter(|_| chars.next().is_none())
+        .and_then(Dir::from_glyph)
+        .ok_or("expected one of ^, >, v and <")?;
+    Ok((parse_point(point)?, dir))
+}
+
+/// Parses the map, returning the starting positions of all guards.
+fn parse(input: &Path) -> (Grid, Vec<Position>) {
     let reader = BufReader::new(File::open(input).unwrap());
-    let mut start = None;
+    let mut guards: Vec<Position> = Vec::new();
     let mut obstructions: Vec<Point> = Vec::new();
     let mut x = 0;
     let mut y = 0;
@@ -185,31 +409,51 @@ fn main() {
         x = 0;
         for c in line.chars() {
             match c {
-                '^' => start = Some((x, y)),
                 '#' => {
                     obstructions.push((x, y));
                 }
-                _ => {}
+                _ => {
+                    if let Some(dir) = Dir::from_glyph(c) {
+                        guards.push(((x, y), dir));
+                    }
+                }
             }
             x += 1;
         }
         y += 1;
     }
     let (width, height) = (x, y);
-    let start = start.unwrap();
-    let grid = Grid::new(width, height, obstructions);
+    assert!(!guards.is_empty(), "no guards");
+    (Grid::new(width, height, obstructions), guards)
+}
+
+struct Patrol {
+    /// The positions visited by the guard, indexed with `Dir::to_index`.
+    visited: Vec<bool>,
+    is_loop: Vec<Status>,
+    /// Whether the guard never leaves the map.
+    loops: bool,
+}
 
-    let mut path_points = vec![false; width * height];
+fn patrol(grid: &Grid, (start, start_dir): Position) -> Patrol {
+    let (width, height) = (grid.width, grid.height);
     let mut is_loop = vec![Status::Unknown; width * height];
     let mut visited = vec![false; width * height * 4];
     let mut tmp_visited = vec![false; width * height * 4];
+    // The candidate obstruction is added and removed again, so that nothing is allocated per
+    // candidate.
+    let mut extended_grid = Overlay::new(grid);
     let mut current = start;
-    let mut dir = Dir::Up;
+    let mut dir = start_dir;
+    let mut loops = false;
     loop {
         let current_index = grid.point_index(current);
-        path_points[current_index] = true;
         let pos = (current, dir);
         let pos_index = dir.to_index(current_index);
+        if visited[pos_index] {
+            loops = true;
+            break;
+        }
         visited[pos_index] = true;
 
         let next = match grid.step(pos) {
@@ -227,7 +471,7 @@ fn main() {
         if next == start || is_loop[next_index] != Status::Unknown {
             continue;
         }
-        let extended_grid = grid.with_obstruction(next);
+        extended_grid.add(next);
         let mut found = Status::NotLoop;
         for (new_point, new_dir) in extended_grid.stops(pos) {
             let new_pos_index = new_dir.to_index(grid.point_index(new_point));
@@ -237,12 +481,247 @@ fn main() {
             }
             tmp_visited[new_pos_index] = true;
         }
+        extended_grid.remove(next);
         tmp_visited.fill(false);
         is_loop[next_index] = found;
     }
+    Patrol {
+        visited,
+        is_loop,
+        loops,
+    }
+}
+
+/// Walks the guard cell by cell and returns the positions of the loop it ends up in, or `None`
+/// if it leaves the map.
+fn trace_loop(map: &Overlay, start: Position) -> Option<Vec<Position>> {
+    let grid = map.grid;
+    let mut first_seen = vec![usize::MAX; grid.width * grid.height * 4];
+    let mut positions: Vec<Position> = Vec::new();
+    let mut pos = start;
+    loop {
+        let pos_index = pos.1.to_index(grid.point_index(pos.0));
+        if first_seen[pos_index] != usize::MAX {
+            return Some(positions.split_off(first_seen[pos_index]));
+        }
+        first_seen[pos_index] = positions.len();
+        positions.push(pos);
+        pos = match map.step(pos) {
+            GridState::OutOfBounds => return None,
+            GridState::Obstruction => (pos.0, pos.1.turn_right()),
+            GridState::Point(next) => (next, pos.1),
+        };
+    }
+}
+
+/// Walks the guard cell by cell and checks whether it ends up in a loop. `seen` must be all
+/// `false` and is left that way.
+fn is_loop_naive(map: &Overlay, start: Position, seen: &mut [bool]) -> bool {
+    let grid = map.grid;
+    let mut seen_indices: Vec<usize> = Vec::new();
+    let mut pos = start;
+    let found = loop {
+        let pos_index = pos.1.to_index(grid.point_index(pos.0));
+        if seen[pos_index] {
+            break true;
+        }
+        seen[pos_index] = true;
+        seen_indices.push(pos_index);
+        pos = match map.step(pos) {
+            GridState::OutOfBounds => break false,
+            GridState::Obstruction => (pos.0, pos.1.turn_right()),
+            GridState::Point(next) => (next, pos.1),
+        };
+    };
+    for index in seen_indices {
+        seen[index] = false;
+    }
+    found
+}
+
+/// Re-simulates every obstruction classified in `is_loop` naively in parallel and returns the
+/// points where the result disagrees, together with the naive result. The other obstructions
+/// are off the path of the guard and can't change it.
+fn verify(grid: &Grid, start: Position, is_loop: &[Status]) -> Vec<(Point, bool)> {
+    let mut disagreements: Vec<(Point, bool)> = (0..grid.width * grid.height)
+        .into_par_iter()
+        .map_init(
+            || {
+                (
+                    vec![false; grid.width * grid.height * 4],
+                    Overlay::new(grid),
+                )
+            },
+            |(seen, map), index| {
+                let point = (index % grid.width, index / grid.width);
+                if is_loop[index] == Status::Unknown {
+                    return None;
+                }
+                map.add(point);
+                let naive = is_loop_naive(map, start, seen);
+                map.remove(point);
+                let fast = is_loop[index] == Status::Loop;
+                (naive != fast).then_some((point, naive))
+            },
+        )
+        .flatten()
+        .collect();
+    disagreements.sort();
+    disagreements
+}
+
+/// Renders the map, drawing the positions in `visited` with `|`, `-` and `+` as in the puzzle
+/// description. `marks` are drawn on top.
+fn render(grid: &Grid, visited: &[bool], marks: &[(Point, char)]) -> String {
+    let mut out = String::with_capacity((grid.width + 1) * grid.height);
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let point = (x, y);
+            let index = grid.point_index(point);
+            let dirs = &visited[Dir::Up.to_index(index)..=Dir::Left.to_index(index)];
+            let vertical = dirs[Dir::Up as usize] || dirs[Dir::Down as usize];
+            let horizontal = dirs[Dir::Right as usize] || dirs[Dir::Left as usize];
+            let c = if let Some(&(_, mark)) = marks.iter().find(|(p, _)| *p == point) {
+                mark
+            } else if grid.is_obstruction(point) {
+                '#'
+            } else {
+                match (vertical, horizontal) {
+                    (true, true) => '+',
+                    (true, false) => '|',
+                    (false, true) => '-',
+                    (false, false) => '.',
+                }
+            };
+            out.push(c);
+        }
+        out.push('\n');
+    }
+    out
+}
 
-    let ans1 = path_points.into_iter().filter(|b| *b).count();
-    let ans2 = is_loop.into_iter().filter(|b| *b == Status::Loop).count();
+fn report_guard(grid: &Grid, start: Position, args: &Cli) {
+    let Patrol {
+        visited,
+        is_loop,
+        loops,
+    } = patrol(grid, start);
+    let ans1 = visited.chunks(4).filter(|s| s.iter().any(|b| *b)).count();
     println!("ans1 = {ans1}");
-    println!("ans2 = {ans2}");
+    // Part 2 asks for obstructions that trap the guard, which means nothing if it is trapped
+    // already.
+    if loops {
+        println!("the guard never leaves the map, so there is no part 2");
+    } else {
+        let ans2 = is_loop.iter().filter(|b| **b == Status::Loop).count();
+        println!("ans2 = {ans2}");
+    }
+
+    if args.verify && !loops {
+        let disagreements = verify(grid, start, &is_loop);
+        for &(point, naive) in &disagreements {
+            let (fast, naive) = match naive {
+                true => ("leaves", "loops"),
+                false => ("loops", "leaves"),
+            };
+            println!(
+                "verify: obstruction at {point:?} {fast} on the fast path but {naive} naively"
+            );
+        }
+        println!("verify: {} disagreements", disagreements.len());
+    }
+
+    if args.render {
+        let mut marks: Vec<(Point, char)> = vec![(start.0, start.1.glyph())];
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                if !loops && is_loop[grid.point_index((x, y))] == Status::Loop {
+                    marks.push(((x, y), 'O'));
+                }
+            }
+        }
+        print!("{}", render(grid, &visited, &marks));
+    }
+
+    if let Some(obstruction) = args.obstruction {
+        if !grid.is_in_bounds(obstruction)
+            || grid.is_obstruction(obstruction)
+            || obstruction == start.0
+        {
+            println!("cannot place an obstruction at {obstruction:?}");
+            return;
+        }
+        let Some(cycle) = trace_loop(&grid.with_obstruction(obstruction), start) else {
+            println!("the guard leaves the map with an obstruction at {obstruction:?}");
+            return;
+        };
+        let mut cycle_visited = vec![false; visited.len()];
+        for &(point, dir) in &cycle {
+            cycle_visited[dir.to_index(grid.point_index(point))] = true;
+        }
+        let turns: Vec<Position> = cycle
+            .iter()
+            .zip(cycle.iter().cycle().skip(1))
+            .filter(|(pos, next)| pos.1 != next.1)
+            .map(|(pos, _)| *pos)
+            .collect();
+        // The cycle also has an entry for every turn in place.
+        let steps = cycle
+            .iter()
+            .zip(cycle.iter().cycle().skip(1))
+            .filter(|(pos, next)| pos.0 != next.0)
+            .count();
+        println!(
+            "loop of {steps} steps with an obstruction at {obstruction:?}, turning at {turns:?}"
+        );
+        print!("{}", render(grid, &cycle_visited, &[(obstruction, 'O')]));
+    }
+}
+
+fn main() {
+    let args = Cli::parse();
+    let (grid, guards) = parse(&args.input);
+
+    if let [guard] = guards[..] {
+        report_guard(&grid, guard, &args);
+    } else {
+        for &guard in &guards {
+            println!("guard at {:?} facing {:?}:", guard.0, guard.1);
+            report_guard(&grid, guard, &args);
+        }
+    }
+
+    let mut map = Overlay::new(&grid);
+    for &point in &args.add {
+        if grid.is_in_bounds(point) {
+            map.add(point);
+        } else {
+            println!("add {point:?}: not on the map");
+        }
+    }
+    for &point in &args.remove {
+        if grid.is_in_bounds(point) {
+            map.remove(point);
+        } else {
+            println!("remove {point:?}: not on the map");
+        }
+    }
+    let mut queries: Vec<Position> = args.query.clone();
+    if !args.add.is_empty() || !args.remove.is_empty() {
+        queries.extend(&guards);
+    }
+
+    let mut simulator = PatrolSimulator::new(&map);
+    for query in queries {
+        if !grid.is_in_bounds(query.0) || map.is_obstruction(query.0) {
+            println!("{query:?}: not a free cell");
+            continue;
+        }
+        match simulator.simulate(query) {
+            Outcome::Exit { steps } => println!("{query:?}: leaves the map after {steps} steps"),
+            Outcome::Loop { steps, period } => {
+                println!("{query:?}: loops after {steps} steps with a period of {period} steps")
+            }
+        }
+    }
 }
@@ -1,30 +1,142 @@
-use std::env;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
+use std::iter;
+use std::path::PathBuf;
+use std::time::Instant;
 
+use clap::{Parser, ValueEnum};
+
+/// A file or a fragment of a file: `(id, offset, size)`.
+type Segment = (usize, usize, usize);
+
+/// Wide enough for the checksums of disks that fit into memory, unlike `usize`.
+type Checksum = u128;
+
+/// Disks up to this size are printed block by block.
+const MAX_BLOCKS_DUMP: usize = 200;
+
+#[derive(Parser)]
+struct Cli {
+    #[arg(required_unless_present = "bench")]
+    input: Option<PathBuf>,
+    /// Print the compacted disk layouts and check the checksums computed from them.
+    #[arg(long)]
+    layout: bool,
+    /// The sizes in the disk map are separated by whitespace or commas instead of being single
+    /// digits, so that they may have several digits.
+    #[arg(long)]
+    separated: bool,
+    /// Instead of solving the input, compare the whole-file compaction algorithms on a random
+    /// disk map with this many digits.
+    #[arg(long, value_name = "DIGITS")]
+    bench: Option<usize>,
+    /// Compact the disk with these allocation policies and report their metrics instead of the
+    /// answers.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    policy: Vec<Policy>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Policy {
+    /// Move single blocks to the leftmost free blocks (part 1).
+    Fragment,
+    /// Move whole files to the leftmost gap that fits them (part 2).
+    FirstFit,
+    /// Move whole files to the smallest gap that fits them.
+    BestFit,
+    /// Move whole files to the largest gap.
+    WorstFit,
+    /// Slide all files to the left, keeping their order, so that the free space is contiguous.
+    Defragment,
+}
+
+impl Policy {
+    fn name(self) -> &'static str {
+        match self {
+            Policy::Fragment => "fragment",
+            Policy::FirstFit => "first-fit",
+            Policy::BestFit => "best-fit",
+            Policy::WorstFit => "worst-fit",
+            Policy::Defragment => "defragment",
+        }
+    }
+}
+
+/// Disk maps above this size are too slow for the linear scan in `solve` to benchmark.
+const MAX_SCAN_BENCH: usize = 200_000;
+
+/// A max segment tree over the sizes of the gaps, ordered by offset.
+struct GapTree {
+    leaves: usize,
+    tree: Vec<usize>,
+}
+
+impl GapTree {
+    pub fn new(sizes: &[usize]) -> Self {
+        let leaves = sizes.len().next_power_of_two();
+        let mut tree = vec![0; 2 * leaves];
+        tree[leaves..leaves + sizes.len()].copy_from_slice(sizes);
+        for i in (1..leaves).rev() {
+            tree[i] = tree[2 * i].max(tree[2 * i + 1]);
+        }
+        Self { leaves, tree }
+    }
+
+    pub fn set(&mut self, index: usize, size: usize) {
+        let mut i = self.leaves + index;
+        self.tree[i] = size;
+        while i > 1 {
+            i /= 2;
+            self.tree[i] = self.tree[2 * i].max(self.tree[2 * i + 1]);
+        }
+    }
+
+    /// Finds the leftmost gap with at least `size` blocks.
+    pub fn find_first(&self, size: usize) -> Option<usize> {
+        if self.tree[1] < size {
+            return None;
+        }
+        let mut i = 1;
+        while i < self.leaves {
+            i = if self.tree[2 * i] >= size {
+                2 * i
+            } else {
+                2 * i + 1
+            };
+        }
+        Some(i - self.leaves)
+    }
+}
+
+/// Compacts the disk, returning the updated checksum and the final layout sorted by offset.
 fn solve<P>(
-    mut checksum: usize,
+    mut checksum: Checksum,
     mut empties: Vec<(usize, usize)>,
-    mut files: Vec<(usize, usize, usize)>,
+    mut files: Vec<Segment>,
     pred: P,
-) -> usize
+) -> (Checksum, Vec<Segment>)
 where
     P: Fn(usize, usize) -> bool,
 {
-    loop {
-        let Some((id, file_offset, file_size)) = files.last_mut() else {
-            break;
-        };
+    let mut layout: Vec<Segment> = Vec::with_capacity(files.len());
+    while let Some((id, file_offset, file_size)) = files.last_mut() {
         let Some((empty_offset, empty_size)) = empties
             .iter_mut()
             .rev()
             .take_while(|block| block.0 < *file_offset)
             .find(|block| pred(block.1, *file_size))
         else {
-            files.pop();
+            layout.extend(files.pop());
             continue;
         };
         let fragment = *file_size.min(empty_size);
-        checksum -= *id * (*file_offset + *file_size - fragment - *empty_offset) * fragment;
+        checksum -= moved_checksum(
+            *id,
+            *file_offset + *file_size - fragment - *empty_offset,
+            fragment,
+        );
+        // The last blocks of the file are moved.
+        layout.push((*id, *empty_offset, fragment));
         *file_size -= fragment;
         if *file_size == 0 {
             files.pop();
@@ -34,41 +146,381 @@ where
         let redundant = empties.iter().rev().take_while(|(_, es)| *es == 0).count();
         empties.truncate(empties.len() - redundant);
     }
-    checksum
+    layout.sort_unstable_by_key(|&(_, offset, _)| offset);
+    (checksum, layout)
 }
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
-    let mut contents = fs::read_to_string(input).unwrap();
-    contents.truncate(contents.trim_end().len());
-    let sizes: Vec<usize> = contents
-        .chars()
-        .map(|c| c.to_digit(10).unwrap() as usize)
-        .collect();
+/// Moves every file to the leftmost gap that fits it, if there is one before the file. Unlike
+/// `solve`, the gap is found with a segment tree, so this takes `O(n log n)` time.
+///
+/// `empties` must be sorted by offset.
+fn solve_whole_files(
+    mut checksum: Checksum,
+    mut empties: Vec<(usize, usize)>,
+    files: Vec<Segment>,
+) -> (Checksum, Vec<Segment>) {
+    let sizes: Vec<usize> = empties.iter().map(|&(_, size)| size).collect();
+    let mut gaps = GapTree::new(&sizes);
+    let mut layout: Vec<Segment> = Vec::with_capacity(files.len());
+    for (id, file_offset, file_size) in files.into_iter().rev() {
+        match gaps.find_first(file_size) {
+            Some(index) if empties[index].0 < file_offset => {
+                let (empty_offset, empty_size) = &mut empties[index];
+                checksum -= moved_checksum(id, file_offset - *empty_offset, file_size);
+                layout.push((id, *empty_offset, file_size));
+                *empty_offset += file_size;
+                *empty_size -= file_size;
+                gaps.set(index, *empty_size);
+            }
+            _ => layout.push((id, file_offset, file_size)),
+        }
+    }
+    layout.sort_unstable_by_key(|&(_, offset, _)| offset);
+    (checksum, layout)
+}
+
+/// Moves every file to the smallest (`best`) or the largest gap before it that fits it, taking
+/// the leftmost one among gaps of the same size.
+///
+/// `empties` must be sorted by offset.
+fn solve_fit(
+    mut checksum: Checksum,
+    empties: &[(usize, usize)],
+    files: &[Segment],
+    best: bool,
+) -> (Checksum, Vec<Segment>) {
+    let mut by_size: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for &(offset, size) in empties {
+        by_size.entry(size).or_default().insert(offset);
+    }
+    let mut layout: Vec<Segment> = Vec::with_capacity(files.len());
+    for &(id, file_offset, file_size) in files.iter().rev() {
+        let fits = |(&size, offsets): (&usize, &BTreeSet<usize>)| {
+            offsets
+                .first()
+                .filter(|&&offset| offset < file_offset)
+                .map(|&offset| (size, offset))
+        };
+        let mut candidates = by_size.range(file_size..);
+        let found = if best {
+            candidates.find_map(fits)
+        } else {
+            candidates.rev().find_map(fits)
+        };
+        let Some((empty_size, empty_offset)) = found else {
+            layout.push((id, file_offset, file_size));
+            continue;
+        };
+        let offsets = by_size.get_mut(&empty_size).unwrap();
+        offsets.remove(&empty_offset);
+        if offsets.is_empty() {
+            by_size.remove(&empty_size);
+        }
+        if empty_size > file_size {
+            by_size
+                .entry(empty_size - file_size)
+                .or_default()
+                .insert(empty_offset + file_size);
+        }
+        checksum -= moved_checksum(id, file_offset - empty_offset, file_size);
+        layout.push((id, empty_offset, file_size));
+    }
+    layout.sort_unstable_by_key(|&(_, offset, _)| offset);
+    (checksum, layout)
+}
+
+/// Slides every file to the left, so that all the free space ends up at the end of the disk.
+fn solve_defragment(mut checksum: Checksum, files: &[Segment]) -> (Checksum, Vec<Segment>) {
+    let mut layout: Vec<Segment> = Vec::with_capacity(files.len());
+    let mut end = 0;
+    for &(id, file_offset, file_size) in files {
+        checksum -= moved_checksum(id, file_offset - end, file_size);
+        layout.push((id, end, file_size));
+        end += file_size;
+    }
+    (checksum, layout)
+}
+
+/// Compacts the disk with `policy`. `empties` and `files` must be sorted by offset.
+fn compact(
+    policy: Policy,
+    checksum: Checksum,
+    empties: &[(usize, usize)],
+    files: &[Segment],
+) -> (Checksum, Vec<Segment>) {
+    match policy {
+        Policy::Fragment => {
+            let empties = empties.iter().rev().copied().collect();
+            solve(checksum, empties, files.to_vec(), |_, _| true)
+        }
+        Policy::FirstFit => solve_whole_files(checksum, empties.to_vec(), files.to_vec()),
+        Policy::BestFit => solve_fit(checksum, empties, files, true),
+        Policy::WorstFit => solve_fit(checksum, empties, files, false),
+        Policy::Defragment => solve_defragment(checksum, files),
+    }
+}
+
+struct Metrics {
+    /// The number of contiguous pieces of files.
+    fragments: usize,
+    /// The number of files split into several pieces.
+    fragmented_files: usize,
+    /// The number of contiguous runs of free blocks.
+    free_runs: usize,
+    largest_free_run: usize,
+    free_blocks: usize,
+}
+
+fn metrics(layout: &[Segment], disk_size: usize) -> Metrics {
+    let mut pieces: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut free_runs = 0;
+    let mut largest_free_run = 0;
+    let mut free_blocks = 0;
+    let mut end = 0;
+    let mut last_id = None;
+    for &(id, offset, size) in layout {
+        if offset > end {
+            free_runs += 1;
+            largest_free_run = largest_free_run.max(offset - end);
+            free_blocks += offset - end;
+        }
+        // Adjacent fragments of the same file form one piece.
+        if offset > end || last_id != Some(id) {
+            *pieces.entry(id).or_default() += 1;
+        }
+        end = offset + size;
+        last_id = Some(id);
+    }
+    if disk_size > end {
+        free_runs += 1;
+        largest_free_run = largest_free_run.max(disk_size - end);
+        free_blocks += disk_size - end;
+    }
+    Metrics {
+        fragments: pieces.values().sum(),
+        fragmented_files: pieces.values().filter(|&&count| count > 1).count(),
+        free_runs,
+        largest_free_run,
+        free_blocks,
+    }
+}
+
+/// Computes the checksum segment by segment, independently of the incremental updates in `solve`.
+fn layout_checksum(layout: &[Segment]) -> Checksum {
+    layout
+        .iter()
+        .map(|&(id, offset, size)| segment_checksum(id, offset, size).unwrap())
+        .sum()
+}
+
+/// Returns `id * (offset + ... + offset + size - 1)`, or `None` if it overflows.
+fn segment_checksum(id: usize, offset: usize, size: usize) -> Option<Checksum> {
+    if size == 0 {
+        return Some(0);
+    }
+    let (id, offset, size) = (id as Checksum, offset as Checksum, size as Checksum);
+    // The sum is `(2 * offset + size - 1) * size / 2`; halve the even factor first, so that
+    // only the result itself can overflow.
+    let twice_mean = 2 * offset + size - 1;
+    let (a, b) = if size.is_multiple_of(2) {
+        (twice_mean, size / 2)
+    } else {
+        (twice_mean / 2, size)
+    };
+    a.checked_mul(b)?.checked_mul(id)
+}
+
+/// Returns how much the checksum decreases when `size` blocks of the file `id` move `distance`
+/// blocks to the left.
+fn moved_checksum(id: usize, distance: usize, size: usize) -> Checksum {
+    id as Checksum * distance as Checksum * size as Checksum
+}
+
+/// Prints the layout block by block, e.g. `0099811188827773336446555566..............`.
+fn render_blocks(layout: &[Segment], disk_size: usize) -> String {
+    let mut out = String::with_capacity(disk_size);
+    for &(id, offset, size) in layout {
+        out.extend(iter::repeat_n('.', offset - out.len()));
+        let c = char::from_digit(id as u32, 10).unwrap();
+        out.extend(iter::repeat_n(c, size));
+    }
+    out.extend(iter::repeat_n('.', disk_size - out.len()));
+    out
+}
+
+/// Prints the layout as runs of `id*size`, with `.` for free space.
+fn render_segments(layout: &[Segment], disk_size: usize) -> String {
+    // Adjacent fragments of the same file are merged.
+    let mut merged: Vec<Segment> = Vec::with_capacity(layout.len());
+    for &(id, offset, size) in layout {
+        match merged.last_mut() {
+            Some((last_id, last_offset, last_size))
+                if *last_id == id && *last_offset + *last_size == offset =>
+            {
+                *last_size += size;
+            }
+            _ => merged.push((id, offset, size)),
+        }
+    }
+    let mut runs: Vec<String> = Vec::with_capacity(2 * merged.len() + 1);
+    let mut end = 0;
+    for (id, offset, size) in merged {
+        if offset > end {
+            runs.push(format!(".*{}", offset - end));
+        }
+        runs.push(format!("{id}*{size}"));
+        end = offset + size;
+    }
+    if disk_size > end {
+        runs.push(format!(".*{}", disk_size - end));
+    }
+    runs.join(" ")
+}
+
+fn print_layout(name: &str, checksum: Checksum, layout: &[Segment], disk_size: usize) {
+    if disk_size <= MAX_BLOCKS_DUMP && layout.iter().all(|&(id, ..)| id < 10) {
+        println!("{name}: {}", render_blocks(layout, disk_size));
+    } else {
+        println!("{name}: {}", render_segments(layout, disk_size));
+    }
+    let expected = layout_checksum(layout);
+    assert_eq!(checksum, expected, "{name}: checksum mismatch");
+}
+
+/// Splits the disk map into files and gaps sorted by offset, also returning the initial checksum
+/// and the size of the disk.
+///
+/// Panics if the disk doesn't fit into `usize` or the checksum into `Checksum`. Compaction only
+/// moves files to the left, so every update to the checksum is bounded by the initial one and
+/// can't overflow either.
+fn parse_disk(sizes: &[usize]) -> (Checksum, Vec<(usize, usize)>, Vec<Segment>, usize) {
     let mut offset = 0;
     let mut empties: Vec<(usize, usize)> = Vec::with_capacity(sizes.len() / 2);
-    let mut files: Vec<(usize, usize, usize)> = Vec::with_capacity((sizes.len() + 1) / 2);
-    let mut checksum: usize = 0;
-    for (i, size) in sizes.into_iter().enumerate() {
+    let mut files: Vec<Segment> = Vec::with_capacity(sizes.len().div_ceil(2));
+    let mut checksum: Checksum = 0;
+    for (i, &size) in sizes.iter().enumerate() {
         if size == 0 {
             continue;
         }
         if i % 2 == 0 {
             let id = i / 2;
             files.push((id, offset, size));
-            checksum += id * (2 * offset + size - 1) * size / 2;
+            checksum = segment_checksum(id, offset, size)
+                .and_then(|sum| checksum.checked_add(sum))
+                .expect("checksum overflow");
         } else {
             empties.push((offset, size));
         }
-        offset += size;
+        offset = offset.checked_add(size).expect("disk size overflow");
     }
-    empties.reverse();
+    (checksum, empties, files, offset)
+}
 
-    let ans1 = solve(checksum, empties.clone(), files.clone(), |_, _| true);
-    println!("ans1 = {ans1}");
+/// Parses a disk map given either as a string of digits, or if `separated`, as sizes separated by
+/// whitespace or commas, which may have several digits.
+fn parse_sizes(contents: &str, separated: bool) -> Vec<usize> {
+    let contents = contents.trim();
+    if separated {
+        let is_separator = |c: char| c == ',' || c.is_whitespace();
+        contents
+            .split(is_separator)
+            .filter(|size| !size.is_empty())
+            .map(|size| {
+                size.parse()
+                    .unwrap_or_else(|err| panic!("invalid size {size:?}: {err}"))
+            })
+            .collect()
+    } else {
+        contents
+            .chars()
+            .map(|c| {
+                c.to_digit(10)
+                    .unwrap_or_else(|| panic!("invalid digit {c:?}")) as usize
+            })
+            .collect()
+    }
+}
+
+/// Generates a random disk map with files of 1 to 9 blocks and gaps of 0 to 9 blocks.
+fn random_sizes(len: usize) -> Vec<usize> {
+    // A fixed xorshift64 sequence, so that every run compacts the same disk.
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    (0..len)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let lowest: usize = if i % 2 == 0 { 1 } else { 0 };
+            lowest + (state % (10 - lowest) as u64) as usize
+        })
+        .collect()
+}
 
-    let ans2 = solve(checksum, empties, files, |size, file_size| {
+fn bench(len: usize) {
+    let sizes = random_sizes(len);
+    let (checksum, empties, files, _) = parse_disk(&sizes);
+    println!("{} files, {} gaps", files.len(), empties.len());
+
+    let start = Instant::now();
+    let (tree_checksum, tree_layout) = solve_whole_files(checksum, empties.clone(), files.clone());
+    println!("segment tree: {:.3}s", start.elapsed().as_secs_f64());
+    assert_eq!(tree_checksum, layout_checksum(&tree_layout));
+    println!("checksum = {tree_checksum}");
+
+    if len > MAX_SCAN_BENCH {
+        println!("linear scan: skipped for more than {MAX_SCAN_BENCH} digits");
+        return;
+    }
+    let mut empties = empties;
+    empties.reverse();
+    let start = Instant::now();
+    let (scan_checksum, scan_layout) = solve(checksum, empties, files, |size, file_size| {
         size >= file_size
     });
+    println!("linear scan: {:.3}s", start.elapsed().as_secs_f64());
+    assert!(tree_layout == scan_layout, "the layouts differ");
+    assert_eq!(tree_checksum, scan_checksum);
+}
+
+fn main() {
+    let args = Cli::parse();
+    if let Some(len) = args.bench {
+        bench(len);
+        return;
+    }
+    let contents = fs::read_to_string(args.input.unwrap()).unwrap();
+    let sizes = parse_sizes(&contents, args.separated);
+    let (checksum, empties, files, disk_size) = parse_disk(&sizes);
+
+    if !args.policy.is_empty() {
+        for &policy in &args.policy {
+            let name = policy.name();
+            let (policy_checksum, layout) = compact(policy, checksum, &empties, &files);
+            let m = metrics(&layout, disk_size);
+            let contiguity = if m.free_blocks == 0 {
+                100.0
+            } else {
+                100.0 * m.largest_free_run as f64 / m.free_blocks as f64
+            };
+            println!(
+                "{name}: checksum = {policy_checksum}, fragments = {}, fragmented files = {}, \
+                 free runs = {}, largest free run = {} of {} blocks ({contiguity:.1}% contiguous)",
+                m.fragments, m.fragmented_files, m.free_runs, m.largest_free_run, m.free_blocks,
+            );
+            if args.layout {
+                print_layout(name, policy_checksum, &layout, disk_size);
+            }
+        }
+        return;
+    }
+
+    let (ans1, layout1) = compact(Policy::Fragment, checksum, &empties, &files);
+    let (ans2, layout2) = compact(Policy::FirstFit, checksum, &empties, &files);
+    println!("ans1 = {ans1}");
     println!("ans2 = {ans2}");
+
+    if args.layout {
+        print_layout("layout1", ans1, &layout1, disk_size);
+        print_layout("layout2", ans2, &layout2, disk_size);
+    }
 }
@@ -1,75 +1,241 @@
 use std::cmp::Ordering::{Equal, Greater};
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, VecDeque};
-use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::mem;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use arrayvec::ArrayVec;
+use clap::{Parser, ValueEnum};
+use num_bigint::BigUint;
 
-type Predecessors = ArrayVec<usize, 3>;
-type Neighbors = ArrayVec<(i32, usize), 3>;
+/// Edge weights and path costs. The costs given on the command line fit into `i32`, so the
+/// cost of a path of up to `2^32` edges can't overflow.
+type Cost = i64;
+type Predecessors = ArrayVec<usize, 4>;
+type Neighbors = ArrayVec<(Cost, usize), 4>;
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
-    let reader = BufReader::new(File::open(input).unwrap());
-    let mut start: Option<usize> = None;
-    let mut end: Option<usize> = None;
-    let mut neighbors: Vec<Neighbors> = Vec::new();
-    let mut prev_walls: Vec<bool> = Vec::new();
-    let mut walls: Vec<bool> = Vec::new();
-    let mut index = 0;
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let width = line.len();
-        if prev_walls.is_empty() {
-            prev_walls.resize(width, true);
-            walls.reserve_exact(width);
-        }
-        for (x, (c, is_wall_above)) in line.chars().zip(prev_walls.drain(..)).enumerate() {
-            let is_wall = c == '#';
-            walls.push(is_wall);
-            if c == 'S' {
-                start = Some(index / 4);
-            } else if c == 'E' {
-                end = Some(index / 4)
+/// Every tile has four nodes, one per direction: west, north, east and south. Turning right
+/// increments the direction.
+const ARROWS: [char; 4] = ['<', '^', '>', 'v'];
+
+#[derive(Parser)]
+struct Cli {
+    #[arg(required_unless_present = "bench")]
+    input: Option<PathBuf>,
+    /// Print the tiles on the best paths, and one best path with its moves.
+    #[arg(long)]
+    render: bool,
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..))]
+    step_cost: i32,
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(i32).range(1..))]
+    turn_cost: i32,
+    /// Allow turning around at this cost.
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+    reverse_cost: Option<i32>,
+    /// The direction the reindeer faces on the start tiles.
+    #[arg(long, value_enum, default_value_t = Facing::East)]
+    facing: Facing,
+    /// Count the best paths which are distinct sequences of tiles.
+    #[arg(long)]
+    count: bool,
+    /// List this many cheapest paths which don't visit a tile twice.
+    #[arg(long)]
+    cheapest: Option<usize>,
+    /// Count the tiles on paths that cost at most this much more than the best ones.
+    #[arg(long)]
+    within: Option<i32>,
+    /// The priority queue used by Dijkstra's algorithm.
+    #[arg(long, value_enum, default_value_t = Queue::Heap)]
+    queue: Queue,
+    /// Time both queues on a generated SIZE by SIZE maze and check that they agree, ignoring
+    /// the input.
+    #[arg(long, value_name = "SIZE")]
+    bench: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Queue {
+    /// A binary heap.
+    Heap,
+    /// A FIFO queue per edge weight (a variant of Dial's algorithm).
+    Buckets,
+}
+
+impl Queue {
+    /// Returns the distances from the nearest of `start_indices` and the predecessors of every
+    /// node on its shortest paths.
+    pub fn dijkstra(
+        self,
+        neighbors: &[Neighbors],
+        start_indices: &[usize],
+    ) -> (Vec<Cost>, Vec<Predecessors>) {
+        match self {
+            Queue::Heap => dijkstra(neighbors, start_indices),
+            Queue::Buckets => dial(neighbors, start_indices),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Facing {
+    West,
+    North,
+    East,
+    South,
+}
+
+struct Costs {
+    step: Cost,
+    turn: Cost,
+    /// The cost of a 180° turn, if it's allowed.
+    reverse: Option<Cost>,
+}
+
+struct Maze {
+    width: usize,
+    walls: Vec<bool>,
+    starts: Vec<usize>,
+    ends: Vec<usize>,
+}
+
+impl Maze {
+    pub fn parse(path: &Path) -> Self {
+        let reader = BufReader::new(File::open(path).unwrap());
+        let mut width = 0;
+        let mut walls: Vec<bool> = Vec::new();
+        let mut starts: Vec<usize> = Vec::new();
+        let mut ends: Vec<usize> = Vec::new();
+        for line in reader.lines() {
+            let line = line.unwrap();
+            width = line.len();
+            for c in line.chars() {
+                if c == 'S' {
+                    starts.push(walls.len());
+                } else if c == 'E' {
+                    ends.push(walls.len());
+                }
+                walls.push(c == '#');
             }
+        }
+        Self::new(width, walls, starts, ends)
+    }
+
+    /// Generates a random `size` by `size` maze with loops, with the start in the bottom left
+    /// corner and the end in the top right one.
+    pub fn generate(size: usize) -> Self {
+        assert!(size >= 3, "the maze is too small");
+        // Tiles with odd coordinates are rooms connected by a random spanning tree.
+        let rooms = (size - 1) / 2;
+        let mut walls = vec![true; size * size];
+        let room = |i: usize| (2 * (i / rooms) + 1) * size + 2 * (i % rooms) + 1;
+        // Knuth's MMIX LCG with a fixed seed, so the benchmark always runs on the same maze. The
+        // low bits of an LCG are poor, hence the shift.
+        let mut state: u64 = 0;
+        let mut random = |bound: usize| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            ((state >> 33) % bound as u64) as usize
+        };
+        let mut seen = vec![false; rooms * rooms];
+        let mut stack = vec![0];
+        seen[0] = true;
+        walls[room(0)] = false;
+        while let Some(&i) = stack.last() {
+            let (x, y) = (i % rooms, i / rooms);
+            let mut next: ArrayVec<usize, 4> = ArrayVec::new();
+            if x > 0 && !seen[i - 1] {
+                next.push(i - 1);
+            }
+            if x + 1 < rooms && !seen[i + 1] {
+                next.push(i + 1);
+            }
+            if y > 0 && !seen[i - rooms] {
+                next.push(i - rooms);
+            }
+            if y + 1 < rooms && !seen[i + rooms] {
+                next.push(i + rooms);
+            }
+            if next.is_empty() {
+                stack.pop();
+                continue;
+            }
+            let j = next[random(next.len())];
+            seen[j] = true;
+            walls[room(j)] = false;
+            walls[(room(i) + room(j)) / 2] = false;
+            stack.push(j);
+        }
+        // Knock down some of the remaining walls between rooms, so that there are many paths.
+        for y in 1..size - 1 {
+            for x in 1..size - 1 {
+                if (x + y) % 2 == 1 && x < 2 * rooms && y < 2 * rooms && random(10) == 0 {
+                    walls[y * size + x] = false;
+                }
+            }
+        }
+        let start = (2 * rooms - 1) * size + 1;
+        let end = size + 2 * rooms - 1;
+        Self::new(size, walls, vec![start], vec![end])
+    }
+
+    fn new(width: usize, walls: Vec<bool>, starts: Vec<usize>, ends: Vec<usize>) -> Self {
+        assert!(!starts.is_empty(), "no start tile");
+        assert!(!ends.is_empty(), "no end tile");
+        Self {
+            width,
+            walls,
+            starts,
+            ends,
+        }
+    }
+
+    /// Builds the graph of the `(tile, direction)` nodes. The maze must be surrounded by walls.
+    pub fn neighbors(&self, costs: &Costs) -> Vec<Neighbors> {
+        let mut neighbors: Vec<Neighbors> = Vec::with_capacity(4 * self.walls.len());
+        for (tile, &is_wall) in self.walls.iter().enumerate() {
+            let index = 4 * tile;
             for dir in 0..4 {
                 let mut node_neighbors: Neighbors = Neighbors::new();
                 if !is_wall {
                     let left_turn = index + ((dir + 3) % 4);
                     let right_turn = index + ((dir + 1) % 4);
-                    node_neighbors.push((1000, left_turn));
-                    node_neighbors.push((1000, right_turn));
+                    node_neighbors.push((costs.turn, left_turn));
+                    node_neighbors.push((costs.turn, right_turn));
+                    if let Some(cost) = costs.reverse {
+                        node_neighbors.push((cost, index + ((dir + 2) % 4)));
+                    }
                 }
                 neighbors.push(node_neighbors);
             }
             if !is_wall {
                 let left = index - 4;
-                if !walls[x - 1] {
-                    neighbors[index].push((1, left));
-                    neighbors[left + 2].push((1, index + 2));
+                if !self.walls[tile - 1] {
+                    neighbors[index].push((costs.step, left));
+                    neighbors[left + 2].push((costs.step, index + 2));
                 }
-                let above = index + 1 - 4 * width;
-                if !is_wall_above {
-                    neighbors[index + 1].push((1, above));
-                    neighbors[above + 2].push((1, index + 3));
+                let above = index + 1 - 4 * self.width;
+                if !self.walls[tile - self.width] {
+                    neighbors[index + 1].push((costs.step, above));
+                    neighbors[above + 2].push((costs.step, index + 3));
                 }
             }
-            index += 4;
         }
-        mem::swap(&mut walls, &mut prev_walls);
+        neighbors
     }
-    let start = start.unwrap();
-    let end = end.unwrap();
+}
 
-    let start_index = 4 * start + 2;
-    let mut dist = vec![i32::MAX; neighbors.len()];
-    dist[start_index] = 0;
+fn dijkstra(neighbors: &[Neighbors], start_indices: &[usize]) -> (Vec<Cost>, Vec<Predecessors>) {
+    let mut dist = vec![Cost::MAX; neighbors.len()];
     let mut visited = vec![false; neighbors.len()];
-    let mut queue: BinaryHeap<Reverse<(i32, usize)>> = BinaryHeap::new();
-    queue.push(Reverse((0, start_index)));
+    let mut queue: BinaryHeap<Reverse<(Cost, usize)>> = BinaryHeap::new();
+    for &start_index in start_indices {
+        dist[start_index] = 0;
+        queue.push(Reverse((0, start_index)));
+    }
     let mut prev: Vec<Predecessors> = vec![Predecessors::new(); neighbors.len()];
     while let Some(Reverse((_prio, index))) = queue.pop() {
         if visited[index] {
@@ -92,27 +258,210 @@ fn main() {
             }
         }
     }
+    (dist, prev)
+}
 
-    let mut end_indices: ArrayVec<usize, 4> = ArrayVec::new();
-    let mut ans1 = i32::MAX;
-    for dir in 0..4 {
-        let end_index = 4 * end + dir;
-        match ans1.cmp(&dist[end_index]) {
-            Greater => {
-                ans1 = dist[end_index];
-                end_indices.clear();
-                end_indices.push(end_index);
+/// Dijkstra's algorithm with a FIFO queue per distinct edge weight, a variant of Dial's bucket
+/// queue. The nodes are settled in the order of their distances, so the nodes pushed along edges
+/// of the same weight are in that order as well, and every queue stays sorted. The next node is
+/// the smallest of the queue fronts. The maze has at most three distinct weights, so both pushing
+/// and popping take constant time, however large the weights are.
+fn dial(neighbors: &[Neighbors], start_indices: &[usize]) -> (Vec<Cost>, Vec<Predecessors>) {
+    let mut weights: Vec<Cost> = Vec::new();
+    for &(weight, _) in neighbors.iter().flatten() {
+        if !weights.contains(&weight) {
+            weights.push(weight);
+        }
+    }
+    // The last queue holds the start nodes.
+    let mut queues: Vec<VecDeque<(Cost, usize)>> = vec![VecDeque::new(); weights.len() + 1];
+    let mut dist = vec![Cost::MAX; neighbors.len()];
+    let mut visited = vec![false; neighbors.len()];
+    for &start_index in start_indices {
+        dist[start_index] = 0;
+        queues[weights.len()].push_back((0, start_index));
+    }
+    let mut prev: Vec<Predecessors> = vec![Predecessors::new(); neighbors.len()];
+    while let Some(next) = (0..queues.len())
+        .filter(|&q| !queues[q].is_empty())
+        .min_by_key(|&q| queues[q][0].0)
+    {
+        let (_dist, index) = queues[next].pop_front().unwrap();
+        if visited[index] {
+            continue;
+        }
+        visited[index] = true;
+        for &(weight, neighbor) in neighbors[index].iter() {
+            let alt = dist[index] + weight;
+            match dist[neighbor].cmp(&alt) {
+                Greater => {
+                    dist[neighbor] = alt;
+                    let q = weights.iter().position(|&w| w == weight).unwrap();
+                    queues[q].push_back((alt, neighbor));
+                    prev[neighbor].clear();
+                    prev[neighbor].push(index);
+                }
+                Equal => {
+                    prev[neighbor].push(index);
+                }
+                _ => {}
             }
-            Equal => {
-                end_indices.push(end_index);
+        }
+    }
+    (dist, prev)
+}
+
+/// Reverses the edges of the graph.
+fn reversed(neighbors: &[Neighbors]) -> Vec<Neighbors> {
+    let mut reversed = vec![Neighbors::new(); neighbors.len()];
+    for (index, node_neighbors) in neighbors.iter().enumerate() {
+        for &(weight, neighbor) in node_neighbors.iter() {
+            reversed[neighbor].push((weight, index));
+        }
+    }
+    reversed
+}
+
+/// Counts the shortest paths from the start nodes to every node. Paths which only differ in how
+/// they turn on a tile, such as `L L` and `R R`, are the same sequence of tiles and count once.
+fn count_paths(dist: &[Cost], prev: &[Predecessors]) -> Vec<BigUint> {
+    let mut order: Vec<usize> = (0..dist.len()).filter(|&i| dist[i] != Cost::MAX).collect();
+    // The weights are positive, so the predecessors come first.
+    order.sort_unstable_by_key(|&i| dist[i]);
+    // `entered[i]` counts the paths whose last move isn't a turn, and `turned_from[i]` has a bit
+    // for every node on the same tile from which `i` is reached by turning only.
+    let mut entered = vec![BigUint::ZERO; dist.len()];
+    let mut turned_from = vec![0u8; dist.len()];
+    let mut counts = vec![BigUint::ZERO; dist.len()];
+    for index in order {
+        let tile = index / 4;
+        let mut count = BigUint::ZERO;
+        if prev[index].is_empty() {
+            count += 1u32;
+        }
+        for &p in &prev[index] {
+            if p / 4 == tile {
+                turned_from[index] |= turned_from[p];
+            } else {
+                count += &counts[p];
             }
-            _ => {}
         }
+        turned_from[index] |= 1 << (index % 4);
+        entered[index] = count;
+        counts[index] = (0..4)
+            .filter(|dir| turned_from[index] & (1 << dir) != 0)
+            .map(|dir| &entered[4 * tile + dir])
+            .sum();
     }
+    counts
+}
 
+/// Finds the cheapest turns from `index` to the other nodes on its tile. Returns the cost and the
+/// previous node of every node on the tile, or `Cost::MAX` if the node can't be reached by
+/// turning. Of equally cheap turns the first one found is kept, so `U` beats `L L`.
+fn tile_turns(neighbors: &[Neighbors], index: usize) -> [(Cost, usize); 4] {
+    let tile = index / 4;
+    let mut turns = [(Cost::MAX, index); 4];
+    turns[index % 4].0 = 0;
+    // Any turn takes at most two edges.
+    for _ in 0..2 {
+        for from in 4 * tile..4 * tile + 4 {
+            let cost = turns[from % 4].0;
+            if cost == Cost::MAX {
+                continue;
+            }
+            for &(weight, to) in neighbors[from].iter() {
+                if to / 4 == tile && cost + weight < turns[to % 4].0 {
+                    turns[to % 4] = (cost + weight, from);
+                }
+            }
+        }
+    }
+    turns
+}
+
+/// Enumerates the `k` cheapest paths from the start nodes to the end nodes, in the order of their
+/// costs. The paths don't visit a tile twice and make at most one turn on every tile, a
+/// combination of turns such as `L L` being the cheapest way to face that direction, so they
+/// are distinct sequences of tiles. The search is guided by `to_end`, the exact distances to the
+/// nearest end node, so only the partial paths cheaper than the `k`-th path are expanded.
+fn cheapest_paths(
+    neighbors: &[Neighbors],
+    start_indices: &[usize],
+    to_end: &[Cost],
+    k: usize,
+) -> Vec<(Cost, Vec<usize>)> {
+    // The partial paths form a tree of `(node, parent link)` links.
+    let mut links: Vec<(usize, Option<usize>)> = Vec::new();
+    let path_nodes = |links: &[(usize, Option<usize>)], link: usize| {
+        iter::successors(Some(link), |&l| links[l].1)
+            .map(|l| links[l].0)
+            .collect::<Vec<usize>>()
+    };
+    let mut queue: BinaryHeap<Reverse<(Cost, Cost, usize)>> = BinaryHeap::new();
+    for &start_index in start_indices {
+        if to_end[start_index] != Cost::MAX {
+            links.push((start_index, None));
+            queue.push(Reverse((to_end[start_index], 0, links.len() - 1)));
+        }
+    }
+    let mut paths: Vec<(Cost, Vec<usize>)> = Vec::with_capacity(k);
+    while paths.len() < k {
+        let Some(Reverse((_prio, cost, link))) = queue.pop() else {
+            break;
+        };
+        let index = links[link].0;
+        if to_end[index] == 0 {
+            let mut path = path_nodes(&links, link);
+            path.reverse();
+            paths.push((cost, path));
+            continue;
+        }
+        let path = path_nodes(&links, link);
+        let tile = index / 4;
+        let has_turned = path.get(1).is_some_and(|&parent| parent / 4 == tile);
+        if !has_turned {
+            let turns = tile_turns(neighbors, index);
+            for dir in 0..4 {
+                let (turn_cost, mut from) = turns[dir];
+                let to = 4 * tile + dir;
+                if to == index || turn_cost == Cost::MAX || to_end[to] == Cost::MAX {
+                    continue;
+                }
+                let mut turn_nodes = vec![to];
+                while from != index {
+                    turn_nodes.push(from);
+                    from = turns[from % 4].1;
+                }
+                let mut turn_link = link;
+                for &node in turn_nodes.iter().rev() {
+                    links.push((node, Some(turn_link)));
+                    turn_link = links.len() - 1;
+                }
+                let alt = cost + turn_cost;
+                queue.push(Reverse((alt + to_end[to], alt, turn_link)));
+            }
+        }
+        for &(weight, neighbor) in neighbors[index].iter() {
+            let is_allowed = neighbor / 4 != tile
+                && to_end[neighbor] != Cost::MAX
+                && path.iter().all(|&node| node / 4 != neighbor / 4);
+            if !is_allowed {
+                continue;
+            }
+            links.push((neighbor, Some(link)));
+            let alt = cost + weight;
+            queue.push(Reverse((alt + to_end[neighbor], alt, links.len() - 1)));
+        }
+    }
+    paths
+}
+
+/// Marks the nodes from which some of `end_indices` are reached by following `prev`.
+fn backtrack(prev: &[Predecessors], end_indices: &[usize]) -> Vec<bool> {
+    let mut visited = vec![false; prev.len()];
     let mut prev_queue: VecDeque<usize> = VecDeque::new();
-    visited.fill(false);
-    for end_index in end_indices {
+    for &end_index in end_indices {
         prev_queue.push_back(end_index);
         visited[end_index] = true;
     }
@@ -124,8 +473,227 @@ fn main() {
             }
         }
     }
-    let ans2 = visited.chunks(4).filter(|s| s.iter().any(|b| *b)).count();
+    visited
+}
+
+/// Returns the nodes of one shortest path to `end_index`, starting from a start node.
+fn best_path(prev: &[Predecessors], end_index: usize) -> Vec<usize> {
+    let mut path = vec![end_index];
+    while let Some(&index) = prev[*path.last().unwrap()].first() {
+        path.push(index);
+    }
+    path.reverse();
+    path
+}
+
+/// Describes the path as a list of moves: `F<n>` for `n` steps forward, `L` and `R` for turns,
+/// `U` for turning around.
+fn moves(path: &[usize]) -> Vec<String> {
+    let mut moves: Vec<String> = Vec::new();
+    let mut steps = 0;
+    for (&from, &to) in path.iter().zip(path.iter().skip(1)) {
+        if from / 4 != to / 4 {
+            steps += 1;
+            continue;
+        }
+        if steps > 0 {
+            moves.push(format!("F{steps}"));
+            steps = 0;
+        }
+        let turn = match (to + 4 - from) % 4 {
+            1 => "R",
+            2 => "U",
+            _ => "L",
+        };
+        moves.push(turn.to_string());
+    }
+    if steps > 0 {
+        moves.push(format!("F{steps}"));
+    }
+    moves
+}
+
+/// Sums the weights of the edges along the path.
+fn path_cost(neighbors: &[Neighbors], path: &[usize]) -> Cost {
+    path.iter()
+        .zip(path.iter().skip(1))
+        .map(|(&from, &to)| {
+            neighbors[from]
+                .iter()
+                .find(|&&(_, neighbor)| neighbor == to)
+                .unwrap()
+                .0
+        })
+        .sum()
+}
+
+fn render(maze: &Maze, marks: &[Option<char>]) -> String {
+    let mut out = String::with_capacity(maze.walls.len() + maze.walls.len() / maze.width);
+    for (tile, &is_wall) in maze.walls.iter().enumerate() {
+        let c = if maze.starts.contains(&tile) {
+            'S'
+        } else if maze.ends.contains(&tile) {
+            'E'
+        } else if is_wall {
+            '#'
+        } else {
+            marks[tile].unwrap_or('.')
+        };
+        out.push(c);
+        if (tile + 1) % maze.width == 0 {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn bench(size: usize) {
+    let start = Instant::now();
+    let maze = Maze::generate(size);
+    let neighbors = maze.neighbors(&Costs {
+        step: 1,
+        turn: 1000,
+        reverse: None,
+    });
+    let start_indices = [4 * maze.starts[0] + 2];
+    println!(
+        "{size}x{size} maze, {} nodes: {:.3}s",
+        neighbors.len(),
+        start.elapsed().as_secs_f64()
+    );
+
+    let mut results: Vec<(Vec<Cost>, Vec<Predecessors>)> = Vec::new();
+    for (name, queue) in [("binary heap", Queue::Heap), ("buckets", Queue::Buckets)] {
+        let start = Instant::now();
+        results.push(queue.dijkstra(&neighbors, &start_indices));
+        println!("{name}: {:.3}s", start.elapsed().as_secs_f64());
+    }
+    let (heap_dist, mut heap_prev) = results.swap_remove(0);
+    let (buckets_dist, mut buckets_prev) = results.swap_remove(0);
+    assert!(heap_dist == buckets_dist, "the distances differ");
+    // The nodes at the same distance are visited in different orders.
+    for prev in heap_prev.iter_mut().chain(buckets_prev.iter_mut()) {
+        prev.sort_unstable();
+    }
+    assert!(heap_prev == buckets_prev, "the predecessors differ");
+    let end_index = (4 * maze.ends[0]..4 * maze.ends[0] + 4)
+        .min_by_key(|&i| heap_dist[i])
+        .unwrap();
+    println!("best cost = {}", heap_dist[end_index]);
+}
+
+fn main() {
+    let args = Cli::parse();
+    if let Some(size) = args.bench {
+        bench(size);
+        return;
+    }
+    let maze = Maze::parse(&args.input.unwrap());
+    let costs = Costs {
+        step: args.step_cost.into(),
+        turn: args.turn_cost.into(),
+        reverse: args.reverse_cost.map(Cost::from),
+    };
+    let neighbors = maze.neighbors(&costs);
+    let start_indices: Vec<usize> = maze
+        .starts
+        .iter()
+        .map(|&start| 4 * start + args.facing as usize)
+        .collect();
+    let (dist, prev) = args.queue.dijkstra(&neighbors, &start_indices);
+
+    let mut end_indices: Vec<usize> = Vec::new();
+    let mut ans1 = Cost::MAX;
+    for end_index in maze.ends.iter().flat_map(|&end| 4 * end..4 * end + 4) {
+        match ans1.cmp(&dist[end_index]) {
+            Greater => {
+                ans1 = dist[end_index];
+                end_indices.clear();
+                end_indices.push(end_index);
+            }
+            Equal => {
+                end_indices.push(end_index);
+            }
+            _ => {}
+        }
+    }
+
+    let visited = backtrack(&prev, &end_indices);
+    let on_best_paths: Vec<bool> = visited.chunks(4).map(|s| s.iter().any(|b| *b)).collect();
+    let ans2 = on_best_paths.iter().filter(|b| **b).count();
 
     println!("ans1 = {ans1}");
     println!("ans2 = {ans2}");
+
+    if args.render {
+        let marks: Vec<Option<char>> = on_best_paths.iter().map(|b| b.then_some('O')).collect();
+        println!("{}", render(&maze, &marks));
+
+        let path = best_path(&prev, end_indices[0]);
+        let mut marks: Vec<Option<char>> = vec![None; maze.walls.len()];
+        // The arrow shows the direction in which the tile is left.
+        for &index in &path {
+            marks[index / 4] = Some(ARROWS[index % 4]);
+        }
+        println!("{}", render(&maze, &marks));
+
+        assert_eq!(
+            path_cost(&neighbors, &path),
+            ans1,
+            "the best path doesn't have the best cost"
+        );
+        println!("moves: {}", moves(&path).join(" "));
+    }
+
+    if args.count {
+        let counts = count_paths(&dist, &prev);
+        let count: BigUint = end_indices.iter().map(|&i| &counts[i]).sum();
+        println!("best paths: {count}");
+    }
+
+    if args.cheapest.is_none() && args.within.is_none() {
+        return;
+    }
+    let end_nodes: Vec<usize> = maze
+        .ends
+        .iter()
+        .flat_map(|&end| 4 * end..4 * end + 4)
+        .collect();
+    let (to_end, _) = args.queue.dijkstra(&reversed(&neighbors), &end_nodes);
+
+    if let Some(k) = args.cheapest {
+        for (i, (cost, path)) in cheapest_paths(&neighbors, &start_indices, &to_end, k)
+            .into_iter()
+            .enumerate()
+        {
+            let start = path[0] / 4;
+            let (x, y) = (start % maze.width, start / maze.width);
+            println!(
+                "path {}: cost {cost} from ({x}, {y}): {}",
+                i + 1,
+                moves(&path).join(" ")
+            );
+        }
+    }
+
+    if let Some(delta) = args.within {
+        // A node is on a path within the budget iff the cheapest path through it is.
+        let budget = ans1 + Cost::from(delta);
+        let is_near = |index: usize| {
+            dist[index] != Cost::MAX
+                && to_end[index] != Cost::MAX
+                && dist[index] + to_end[index] <= budget
+        };
+        let near: Vec<bool> = (0..maze.walls.len())
+            .map(|tile| (4 * tile..4 * tile + 4).any(is_near))
+            .collect();
+        println!(
+            "tiles within {delta}: {}",
+            near.iter().filter(|b| **b).count()
+        );
+        if args.render {
+            let marks: Vec<Option<char>> = near.iter().map(|b| b.then_some('O')).collect();
+            println!("{}", render(&maze, &marks));
+        }
+    }
 }
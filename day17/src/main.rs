@@ -1,10 +1,13 @@
 use core::panic;
-use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::mem;
+use std::path::PathBuf;
+use std::time::Instant;
 
+use clap::Parser;
 use itertools::{izip, Itertools};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use strum::FromRepr;
 
 type Word = u64;
@@ -22,6 +25,47 @@ enum Instr {
     Cdv = 7,
 }
 
+/// A combo operand resolved at compile time.
+#[derive(Clone, Copy)]
+enum Operand {
+    Literal(u8),
+    Reg(usize),
+    Reserved,
+}
+
+impl Operand {
+    fn decode(literal: u8) -> Self {
+        match literal {
+            0..=3 => Operand::Literal(literal),
+            4..=6 => Operand::Reg((literal & 3).into()),
+            _ => Operand::Reserved,
+        }
+    }
+}
+
+/// A pre-decoded instruction. Jump targets are kept as instruction pointers. The instructions
+/// with a combo operand have a variant for a literal operand, whose shift or output is known in
+/// advance, and one for a register operand.
+#[derive(Clone, Copy)]
+enum Op {
+    AdvLit(u32),
+    Adv(usize),
+    Bxl(Word),
+    BstLit(Word),
+    Bst(usize),
+    Jnz(usize),
+    Bxc,
+    OutLit(u8),
+    Out(usize),
+    BdvLit(u32),
+    Bdv(usize),
+    CdvLit(u32),
+    Cdv(usize),
+    /// An instruction with the reserved combo operand. Only fails when executed, since odd
+    /// positions are decoded as well.
+    Reserved,
+}
+
 #[derive(Debug, Clone)]
 enum Expr {
     Literal(Word),
@@ -47,15 +91,169 @@ struct Machine {
     code: Vec<u8>,
 }
 
+/// A program decoded once so that it can be executed many times without going through
+/// `Instr::from_repr` on every step.
+struct Program {
+    // `ops[ip]` is the instruction starting at `ip`, so that odd jump targets work as well.
+    ops: Vec<Op>,
+}
+
+impl Program {
+    /// Executes the program, passing every output to `emit`. Stops early if `emit` returns
+    /// `false`.
+    #[inline(always)]
+    fn execute<F: FnMut(u8) -> bool>(&self, mut regs: [Word; 3], mut emit: F) {
+        let end = self.ops.len();
+        let mut ip = 0;
+        while ip < end {
+            match self.ops[ip] {
+                // The literal shifts are below 4, so they can't overflow.
+                Op::AdvLit(shift) => {
+                    regs[0] >>= shift;
+                }
+                Op::Adv(reg) => {
+                    regs[0] = div_pow2(regs[0], regs[reg]);
+                }
+                Op::Bxl(literal) => {
+                    regs[1] ^= literal;
+                }
+                Op::BstLit(literal) => {
+                    regs[1] = literal;
+                }
+                Op::Bst(reg) => {
+                    regs[1] = regs[reg] & 7;
+                }
+                Op::Jnz(target) => {
+                    if regs[0] != 0 {
+                        ip = target;
+                        continue;
+                    }
+                }
+                Op::Bxc => {
+                    regs[1] ^= regs[2];
+                }
+                Op::OutLit(literal) => {
+                    if !emit(literal) {
+                        return;
+                    }
+                }
+                Op::Out(reg) => {
+                    if !emit((regs[reg] & 7) as u8) {
+                        return;
+                    }
+                }
+                Op::BdvLit(shift) => {
+                    regs[1] = regs[0] >> shift;
+                }
+                Op::Bdv(reg) => {
+                    regs[1] = div_pow2(regs[0], regs[reg]);
+                }
+                Op::CdvLit(shift) => {
+                    regs[2] = regs[0] >> shift;
+                }
+                Op::Cdv(reg) => {
+                    regs[2] = div_pow2(regs[0], regs[reg]);
+                }
+                Op::Reserved => panic!("reserved"),
+            }
+            ip += 2;
+        }
+    }
+
+    pub fn run(&self, regs: [Word; 3]) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::new();
+        self.execute(regs, |value| {
+            out.push(value);
+            true
+        });
+        out
+    }
+
+    /// Checks whether the program outputs exactly `target`, bailing out on the first mismatch.
+    pub fn outputs(&self, regs: [Word; 3], target: &[u8]) -> bool {
+        let mut pos = 0;
+        let mut matches = true;
+        self.execute(regs, |value| {
+            matches = target.get(pos) == Some(&value);
+            pos += 1;
+            matches
+        });
+        matches && pos == target.len()
+    }
+
+    /// Finds the smallest A in `range` for which the program outputs `target`, trying the
+    /// values in parallel.
+    pub fn brute_force(
+        &self,
+        range: std::ops::Range<Word>,
+        [_, b, c]: [Word; 3],
+        target: &[u8],
+    ) -> Option<Word> {
+        range
+            .into_par_iter()
+            .find_first(|&a| self.outputs([a, b, c], target))
+    }
+}
+
+/// Computes `value / 2^shift`, which is zero for shifts past the word size.
+#[inline(always)]
+fn div_pow2(value: Word, shift: Word) -> Word {
+    u32::try_from(shift)
+        .ok()
+        .and_then(|shift| value.checked_shr(shift))
+        .unwrap_or(0)
+}
+
 impl Machine {
     pub fn new(code: Vec<u8>) -> Self {
         Machine { code }
     }
 
-    pub fn run1(&self, mut regs: [Word; 3]) -> Vec<u8> {
+    pub fn compile(&self) -> Program {
+        let ops = self
+            .code
+            .windows(2)
+            .map(|w| {
+                let literal = w[1];
+                match (Instr::from_repr(w[0]).unwrap(), Operand::decode(literal)) {
+                    (Instr::Bxl, _) => Op::Bxl(literal.into()),
+                    (Instr::Jnz, _) => Op::Jnz(literal.into()),
+                    (Instr::Bxc, _) => Op::Bxc,
+                    (_, Operand::Reserved) => Op::Reserved,
+                    (Instr::Adv, Operand::Literal(shift)) => Op::AdvLit(shift.into()),
+                    (Instr::Adv, Operand::Reg(reg)) => Op::Adv(reg),
+                    (Instr::Bst, Operand::Literal(value)) => Op::BstLit(value.into()),
+                    (Instr::Bst, Operand::Reg(reg)) => Op::Bst(reg),
+                    (Instr::Out, Operand::Literal(value)) => Op::OutLit(value),
+                    (Instr::Out, Operand::Reg(reg)) => Op::Out(reg),
+                    (Instr::Bdv, Operand::Literal(shift)) => Op::BdvLit(shift.into()),
+                    (Instr::Bdv, Operand::Reg(reg)) => Op::Bdv(reg),
+                    (Instr::Cdv, Operand::Literal(shift)) => Op::CdvLit(shift.into()),
+                    (Instr::Cdv, Operand::Reg(reg)) => Op::Cdv(reg),
+                }
+            })
+            .collect();
+        Program { ops }
+    }
+
+    /// Like `Program::outputs`, but interpreting the code directly.
+    pub fn outputs1(&self, regs: [Word; 3], target: &[u8]) -> bool {
+        let mut pos = 0;
+        let mut matches = true;
+        self.interpret(regs, |value| {
+            matches = target.get(pos) == Some(&value);
+            pos += 1;
+            matches
+        });
+        matches && pos == target.len()
+    }
+
+    /// Interprets the code, passing every output to `emit`. Stops early if `emit` returns
+    /// `false`.
+    #[inline(always)]
+    fn interpret<F: FnMut(u8) -> bool>(&self, mut regs: [Word; 3], mut emit: F) {
         let end = self.code.len();
         let mut ip = 0;
-        let mut out: Vec<u8> = Vec::new();
         while ip < end {
             let instr = Instr::from_repr(self.code[ip]).unwrap();
             let literal: Word = self.code[ip + 1].into();
@@ -66,7 +264,7 @@ impl Machine {
             };
             match instr {
                 Instr::Adv => {
-                    regs[0] /= 1 << combo;
+                    regs[0] = div_pow2(regs[0], combo);
                 }
                 Instr::Bxl => {
                     regs[1] ^= literal;
@@ -85,18 +283,19 @@ impl Machine {
                 }
                 Instr::Out => {
                     let result = (combo & 7) as u8;
-                    out.push(result);
+                    if !emit(result) {
+                        return;
+                    }
                 }
                 Instr::Bdv => {
-                    regs[1] = regs[0] / (1 << combo);
+                    regs[1] = div_pow2(regs[0], combo);
                 }
                 Instr::Cdv => {
-                    regs[2] = regs[0] / (1 << combo);
+                    regs[2] = div_pow2(regs[0], combo);
                 }
             }
             ip += 2;
         }
-        out
     }
 
     pub fn run2(&self) -> State {
@@ -238,30 +437,49 @@ fn calculate(expr: &Expr, a_value: Word) -> Word {
     }
 }
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
-    let reader = BufReader::new(File::open(input).unwrap());
-    let mut lines = reader.lines();
-    let regs: [Word; 3] = (&mut lines)
-        .take(3)
-        .map(|line| {
-            let line = line.unwrap();
-            extract_value(&line).parse().unwrap()
-        })
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap();
-    let line = lines.nth(1).unwrap().unwrap();
-    let code: Vec<u8> = extract_value(&line)
-        .split(',')
-        .map(|x| x.parse().unwrap())
-        .collect();
+#[derive(Parser)]
+struct Cli {
+    input: PathBuf,
+    /// Measure how fast the interpreter and the compiled program check this many values of A
+    /// against the program, as `--brute` does.
+    #[arg(long)]
+    bench: Option<Word>,
+    /// Find the answer for part 2 by trying every A below this bound instead of solving the
+    /// equations.
+    #[arg(long)]
+    brute: Option<Word>,
+}
 
-    let machine = Machine::new(code.clone());
+fn bench(machine: &Machine, program: &Program, [_, b, c]: [Word; 3], runs: Word) {
+    let report = |name: &str, start: Instant, matches: usize| {
+        let elapsed = start.elapsed().as_secs_f64();
+        let throughput = runs as f64 / elapsed;
+        println!("{name}: {elapsed:.3}s, {throughput:.0} runs/s ({matches} matches)");
+    };
 
-    let out = machine.run1(regs);
-    let ans1 = out.iter().join(",");
+    let target = &machine.code;
+
+    let start = Instant::now();
+    let matches = (0..runs)
+        .filter(|&a| machine.outputs1([a, b, c], target))
+        .count();
+    report("interpreter", start, matches);
+
+    let start = Instant::now();
+    let matches = (0..runs)
+        .filter(|&a| program.outputs([a, b, c], target))
+        .count();
+    report("compiled", start, matches);
+
+    let start = Instant::now();
+    let matches = (0..runs)
+        .into_par_iter()
+        .filter(|&a| program.outputs([a, b, c], target))
+        .count();
+    report("compiled (parallel)", start, matches);
+}
 
+fn solve(machine: &Machine, code: &[u8]) -> Word {
     let state = machine.run2();
     let mut equations: Vec<(Expr, Word, Word)> = Vec::with_capacity(state.out.len());
     for (expr, target, offset) in izip!(state.out.iter(), code.iter(), (0..).step_by(3)) {
@@ -284,8 +502,46 @@ fn main() {
         }
         mem::swap(&mut a_values, &mut new_a_values);
     }
-    let ans2 = *a_values.iter().min().unwrap();
-    assert_eq!(machine.run1([ans2, 0, 0]), code);
+    *a_values.iter().min().unwrap()
+}
+
+fn main() {
+    let args = Cli::parse();
+    let reader = BufReader::new(File::open(args.input).unwrap());
+    let mut lines = reader.lines();
+    let regs: [Word; 3] = (&mut lines)
+        .take(3)
+        .map(|line| {
+            let line = line.unwrap();
+            extract_value(&line).parse().unwrap()
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap();
+    let line = lines.nth(1).unwrap().unwrap();
+    let code: Vec<u8> = extract_value(&line)
+        .split(',')
+        .map(|x| x.parse().unwrap())
+        .collect();
+
+    let machine = Machine::new(code.clone());
+    let program = machine.compile();
+
+    if let Some(runs) = args.bench {
+        bench(&machine, &program, regs, runs);
+        return;
+    }
+
+    let out = program.run(regs);
+    let ans1 = out.iter().join(",");
+
+    let ans2 = match args.brute {
+        Some(bound) => program
+            .brute_force(0..bound, regs, &code)
+            .expect("no solution below the bound"),
+        None => solve(&machine, &code),
+    };
+    assert!(program.outputs([ans2, regs[1], regs[2]], &code));
 
     println!("ans1 = {ans1}");
     println!("ans2 = {ans2}");
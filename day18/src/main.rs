@@ -1,14 +1,47 @@
 use std::collections::VecDeque;
-use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::iter;
+use std::path::PathBuf;
 
+use anyhow::{bail, ensure, Context};
+use clap::Parser;
 use itertools::Itertools;
 
 type Point = (usize, usize);
 
 const INF: usize = usize::MAX;
 
+/// The size of the example grid, on which part 1 uses fewer bytes.
+const EXAMPLE_SIZE: usize = 7;
+
+#[derive(Parser)]
+struct Cli {
+    input: PathBuf,
+    /// The width and height of the memory space. By default, the smallest size that fits all
+    /// the bytes.
+    #[arg(long)]
+    size: Option<usize>,
+    /// The number of fallen bytes in part 1. By default, 12 for the 7x7 example and 1024
+    /// otherwise.
+    #[arg(long)]
+    bytes: Option<usize>,
+    /// Find the first blocking byte with a union-find over the fallen bytes instead of a binary
+    /// search, and print the barrier it completes.
+    #[arg(long)]
+    union_find: bool,
+    /// Walk one cell per tick while the bytes keep falling, one per tick, and print the earliest
+    /// arrival at the exit. Waiting is pointless, as the bytes only ever block cells.
+    #[arg(long)]
+    walk: bool,
+    /// Draw the grid after this many bytes fall.
+    #[arg(long)]
+    render: Option<usize>,
+    /// Draw the grid every this many bytes until the first blocking byte falls.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    animate: Option<u64>,
+}
+
 struct Grid {
     size: usize,
     grid: Vec<usize>,
@@ -36,24 +69,71 @@ impl Grid {
         }
     }
 
-    pub fn add(&mut self, point: Point, value: usize) {
-        let index = self.to_index(point);
+    /// Marks the byte at `(x, y)` as falling at time `value`, unless an earlier one fell there.
+    pub fn add(&mut self, (x, y): Point, value: usize) -> anyhow::Result<()> {
+        // The grid is surrounded by a border of walls.
+        let inner_size = self.size - 2;
+        ensure!(
+            x < inner_size && y < inner_size,
+            "byte {x},{y} is outside of the {inner_size}x{inner_size} grid"
+        );
+        let index = self.to_index((x + 1, y + 1));
         if self.grid[index] == INF {
             self.grid[index] = value;
         }
+        Ok(())
     }
 
-    pub fn run(&mut self, threshold: usize) -> Option<usize> {
+    /// Returns a shortest path from the start to the exit avoiding the bytes that fall up to
+    /// `threshold`.
+    pub fn run(&mut self, threshold: usize) -> Option<Vec<Point>> {
+        self.flood(threshold, true);
+        self.path_to_end()
+    }
+
+    /// Draws the grid after the bytes up to `threshold` fall, with the bytes as `#`, the shortest
+    /// path as `O` and the cells unreachable from the start as `░`.
+    pub fn render(&mut self, threshold: usize) -> String {
+        let mut on_path = vec![false; self.grid.len()];
+        for (x, y) in self.run(threshold).unwrap_or_default() {
+            on_path[self.to_index((x + 1, y + 1))] = true;
+        }
+        self.flood(threshold, false);
+        let inner_size = self.size - 2;
+        let mut out = String::with_capacity(inner_size * (inner_size + 1));
+        for y in 0..inner_size {
+            for x in 0..inner_size {
+                let index = self.to_index((x + 1, y + 1));
+                out.push(if on_path[index] {
+                    'O'
+                } else if self.grid[index] <= threshold {
+                    '#'
+                } else if self.dist[index] == INF {
+                    '░'
+                } else {
+                    '.'
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Computes the distances from the start, stopping at the exit if `stop_at_end`.
+    fn flood(&mut self, threshold: usize, stop_at_end: bool) {
         let start_index = self.to_index((1, 1));
         let end_index = self.to_index((self.size - 2, self.size - 2));
 
         self.dist.fill(INF);
         self.queue.clear();
+        if self.grid[start_index] <= threshold {
+            return;
+        }
         self.dist[start_index] = 0;
         self.queue.push_back(start_index);
         while let Some(index) = self.queue.pop_front() {
-            if index == end_index {
-                return Some(self.dist[end_index]);
+            if stop_at_end && index == end_index {
+                return;
             }
             for next_index in [index - 1, index - self.size, index + 1, index + self.size] {
                 if self.dist[next_index] != INF || self.grid[next_index] <= threshold {
@@ -63,7 +143,58 @@ impl Grid {
                 self.queue.push_back(next_index);
             }
         }
-        None
+    }
+
+    /// Finds the earliest arrival at the exit when the walker makes a step every tick, while the
+    /// byte with value `t` falls at tick `t`. A cell can't be entered at or after the tick its byte
+    /// falls. Returns the position of the walker at every tick.
+    ///
+    /// The cells only ever get blocked, so reaching a cell earlier is never worse, and waiting
+    /// never helps: a BFS that only enters the cells still free at the arrival tick suffices.
+    pub fn walk(&mut self) -> Option<Vec<Point>> {
+        let start_index = self.to_index((1, 1));
+        let end_index = self.to_index((self.size - 2, self.size - 2));
+
+        self.dist.fill(INF);
+        self.queue.clear();
+        self.dist[start_index] = 0;
+        self.queue.push_back(start_index);
+        while let Some(index) = self.queue.pop_front() {
+            if index == end_index {
+                break;
+            }
+            let time = self.dist[index] + 1;
+            for next_index in [index - 1, index - self.size, index + 1, index + self.size] {
+                if self.dist[next_index] != INF || self.grid[next_index] <= time {
+                    continue;
+                }
+                self.dist[next_index] = time;
+                self.queue.push_back(next_index);
+            }
+        }
+        self.path_to_end()
+    }
+
+    /// Follows the distances computed by the last search back from the exit.
+    fn path_to_end(&self) -> Option<Vec<Point>> {
+        let end_index = self.to_index((self.size - 2, self.size - 2));
+        if self.dist[end_index] == INF {
+            return None;
+        }
+        let mut path: Vec<Point> = Vec::with_capacity(self.dist[end_index] + 1);
+        let mut index = end_index;
+        loop {
+            path.push((index % self.size - 1, index / self.size - 1));
+            if self.dist[index] == 0 {
+                break;
+            }
+            index = [index - 1, index - self.size, index + 1, index + self.size]
+                .into_iter()
+                .find(|&prev_index| self.dist[prev_index] == self.dist[index] - 1)
+                .unwrap();
+        }
+        path.reverse();
+        Some(path)
     }
 
     fn to_index(&self, (x, y): Point) -> usize {
@@ -71,45 +202,225 @@ impl Grid {
     }
 }
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
-    let reader = BufReader::new(File::open(&input).unwrap());
+/// Disjoint sets of fallen bytes, which are connected diagonally too. Two extra sets stand for
+/// the top and right borders and for the left and bottom ones: the exit is unreachable iff a
+/// chain of bytes connects them.
+struct Barriers {
+    size: usize,
+    parent: Vec<usize>,
+    set_size: Vec<usize>,
+    fallen: Vec<bool>,
+}
 
-    let (size, threshold1) = if input.into_string().is_ok_and(|s| s.starts_with("ex")) {
-        (7 + 2, 12)
-    } else {
-        (71 + 2, 1024)
-    };
-    let mut grid = Grid::new(size);
+impl Barriers {
+    pub fn new(size: usize) -> Self {
+        let nodes = size * size + 2;
+        Self {
+            size,
+            parent: (0..nodes).collect(),
+            set_size: vec![1; nodes],
+            fallen: vec![false; size * size],
+        }
+    }
 
+    /// Drops a byte, returning whether the exit has become unreachable.
+    pub fn add(&mut self, point: Point) -> bool {
+        let index = self.to_index(point);
+        if !self.fallen[index] {
+            self.fallen[index] = true;
+            let (top_right, bottom_left) = self.borders(point);
+            if top_right {
+                self.union(index, self.size * self.size);
+            }
+            if bottom_left {
+                self.union(index, self.size * self.size + 1);
+            }
+            for neighbor in Self::neighbors(self.size, point) {
+                let neighbor_index = self.to_index(neighbor);
+                if self.fallen[neighbor_index] {
+                    self.union(index, neighbor_index);
+                }
+            }
+        }
+        self.find(self.size * self.size) == self.find(self.size * self.size + 1)
+    }
+
+    /// Returns a shortest chain of fallen bytes from the top or right border to the left or bottom
+    /// one.
+    pub fn chain(&self) -> Option<Vec<Point>> {
+        let mut prev: Vec<Option<usize>> = vec![None; self.fallen.len()];
+        let mut seen = vec![false; self.fallen.len()];
+        let mut queue: VecDeque<Point> = VecDeque::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let index = self.to_index((x, y));
+                if self.fallen[index] && self.borders((x, y)).0 {
+                    seen[index] = true;
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        while let Some(point) = queue.pop_front() {
+            if self.borders(point).1 {
+                let chain = iter::successors(Some(self.to_index(point)), |&i| prev[i])
+                    .map(|i| (i % self.size, i / self.size))
+                    .collect();
+                return Some(chain);
+            }
+            for neighbor in Self::neighbors(self.size, point) {
+                let neighbor_index = self.to_index(neighbor);
+                if self.fallen[neighbor_index] && !seen[neighbor_index] {
+                    seen[neighbor_index] = true;
+                    prev[neighbor_index] = Some(self.to_index(point));
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns whether the point touches the top or right border and the left or bottom one.
+    fn borders(&self, (x, y): Point) -> (bool, bool) {
+        let last = self.size - 1;
+        (y == 0 || x == last, x == 0 || y == last)
+    }
+
+    /// Returns the eight neighbors of the point inside the grid.
+    fn neighbors(size: usize, (x, y): Point) -> impl Iterator<Item = Point> {
+        (-1..=1)
+            .cartesian_product(-1..=1)
+            .filter(|&d| d != (0, 0))
+            .filter_map(move |(dx, dy)| {
+                let nx = x.checked_add_signed(dx).filter(|&nx| nx < size)?;
+                let ny = y.checked_add_signed(dy).filter(|&ny| ny < size)?;
+                Some((nx, ny))
+            })
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parent[index] != index {
+            self.parent[index] = self.parent[self.parent[index]];
+            index = self.parent[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.set_size[a] < self.set_size[b] {
+            (a, b) = (b, a);
+        }
+        self.parent[b] = a;
+        self.set_size[a] += self.set_size[b];
+    }
+
+    fn to_index(&self, (x, y): Point) -> usize {
+        y * self.size + x
+    }
+}
+
+fn parse_bytes(path: &PathBuf) -> anyhow::Result<Vec<Point>> {
+    let reader = BufReader::new(File::open(path)?);
     let mut bytes: Vec<Point> = Vec::new();
     for (i, line) in reader.lines().enumerate() {
-        let line = line.unwrap();
-        let point: Point = line
-            .split(',')
-            .map(|s| s.parse().unwrap())
-            .collect_tuple()
-            .unwrap();
+        let line = line?;
+        let Some((x, y)) = line.split(',').map(str::parse).collect_tuple() else {
+            bail!("line {}: expected X,Y, got {line:?}", i + 1);
+        };
+        let point = (
+            x.with_context(|| format!("line {}: invalid X", i + 1))?,
+            y.with_context(|| format!("line {}: invalid Y", i + 1))?,
+        );
         bytes.push(point);
-        grid.add((point.0 + 1, point.1 + 1), i + 1);
     }
+    Ok(bytes)
+}
 
-    let ans1 = grid.run(threshold1).unwrap();
-    println!("ans1 = {ans1}");
+fn main() -> anyhow::Result<()> {
+    let args = Cli::parse();
+    let bytes = parse_bytes(&args.input)?;
 
-    let mut lower = 0;
-    let mut upper = bytes.len() + 1;
-    while upper - lower > 1 {
-        let threshold = lower + (upper - lower) / 2;
-        match grid.run(threshold) {
-            Some(_) => {
-                lower = threshold;
+    let size = match args.size {
+        Some(size) => size,
+        None => bytes.iter().map(|&(x, y)| x.max(y) + 1).max().unwrap_or(0),
+    };
+    ensure!(size > 0, "the grid is empty");
+    let threshold1 = args
+        .bytes
+        .unwrap_or(if size == EXAMPLE_SIZE { 12 } else { 1024 });
+    let mut grid = Grid::new(size + 2);
+    for (i, &point) in bytes.iter().enumerate() {
+        grid.add(point, i + 1)?;
+    }
+
+    if args.walk {
+        match grid.walk() {
+            Some(route) => {
+                println!("arrival at tick {}", route.len() - 1);
+                println!(
+                    "route: {}",
+                    route.iter().map(|(x, y)| format!("{x},{y}")).join(" ")
+                );
             }
-            None => {
-                upper = threshold;
+            None => println!("the exit is never reached"),
+        }
+        return Ok(());
+    }
+
+    let Some(path) = grid.run(threshold1) else {
+        bail!("the exit is unreachable after {threshold1} bytes");
+    };
+    let ans1 = path.len() - 1;
+    println!("ans1 = {ans1}");
+
+    // The number of bytes after which the exit is still reachable.
+    let reachable = if args.union_find {
+        let mut barriers = Barriers::new(size);
+        let reachable = bytes
+            .iter()
+            .position(|&point| barriers.add(point))
+            .unwrap_or(bytes.len());
+        if let Some(chain) = barriers.chain() {
+            println!(
+                "barrier of {} bytes: {}",
+                chain.len(),
+                chain.iter().map(|(x, y)| format!("{x},{y}")).join(" ")
+            );
+        }
+        reachable
+    } else {
+        let mut lower = 0;
+        let mut upper = bytes.len() + 1;
+        while upper - lower > 1 {
+            let threshold = lower + (upper - lower) / 2;
+            match grid.run(threshold) {
+                Some(_) => {
+                    lower = threshold;
+                }
+                None => {
+                    upper = threshold;
+                }
             }
         }
+        lower
+    };
+    match bytes.get(reachable) {
+        Some(ans2) => println!("ans2 = {},{}", ans2.0, ans2.1),
+        None => println!("ans2: the exit is never blocked"),
+    }
+
+    if let Some(threshold) = args.render {
+        print!("{}", grid.render(threshold));
+    }
+    if let Some(step) = args.animate {
+        let last = (reachable + 1).min(bytes.len());
+        for threshold in (0..last).step_by(step as usize).chain([last]) {
+            println!("after {threshold} bytes:");
+            println!("{}", grid.render(threshold));
+        }
     }
-    let ans2 = bytes[lower];
-    println!("ans2 = {},{}", ans2.0, ans2.1);
+    Ok(())
 }
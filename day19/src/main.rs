@@ -1,6 +1,111 @@
-use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use clap::{Parser, ValueEnum};
+
+/// Marks the bytes that don't occur in any towel.
+const NO_COLOR: usize = usize::MAX;
+
+/// The colors the benchmark's towels and designs are made of.
+const BENCH_COLORS: &[u8] = b"wubrg";
+const BENCH_DESIGNS: usize = 400;
+
+/// A towel or a design.
+type Stripes = Box<[u8]>;
+
+#[derive(Parser)]
+struct Cli {
+    #[arg(required_unless_present = "bench")]
+    input: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = Matcher::Trie)]
+    matcher: Matcher,
+    /// Time both matchers on TOWELS random towels and a fixed number of random designs, and
+    /// check that their answers agree.
+    #[arg(long, value_name = "TOWELS")]
+    bench: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Matcher {
+    /// Walk a trie of the towels.
+    Trie,
+    /// Binary search the sorted towels.
+    Sorted,
+}
+
+/// A trie of the towels, over the colors that occur in them.
+struct Trie {
+    /// Maps the bytes to the indices of the colors.
+    colors: [usize; 256],
+    num_colors: usize,
+    /// `num_colors` children per node; 0 means no child, as the root is nobody's child.
+    children: Vec<usize>,
+    /// Whether a towel ends at the node.
+    terminal: Vec<bool>,
+}
+
+impl Trie {
+    pub fn new(towels: &[Stripes]) -> Self {
+        let mut colors = [NO_COLOR; 256];
+        let mut num_colors = 0;
+        for &c in towels.iter().flat_map(|towel| towel.iter()) {
+            if colors[c as usize] == NO_COLOR {
+                colors[c as usize] = num_colors;
+                num_colors += 1;
+            }
+        }
+        let mut trie = Self {
+            colors,
+            num_colors,
+            children: vec![0; num_colors],
+            terminal: vec![false],
+        };
+        for towel in towels {
+            let mut node = 0;
+            for &c in towel.iter() {
+                let child = node * num_colors + colors[c as usize];
+                if trie.children[child] == 0 {
+                    trie.children[child] = trie.terminal.len();
+                    trie.children.resize(trie.children.len() + num_colors, 0);
+                    trie.terminal.push(false);
+                }
+                node = trie.children[child];
+            }
+            trie.terminal[node] = true;
+        }
+        trie
+    }
+
+    /// Counts the ways to arrange the design, from its end to its start. `count[pos]` is the
+    /// number of arrangements of `design[pos..]`.
+    pub fn count(&self, design: &[u8], count: &mut Vec<i64>) -> i64 {
+        let len = design.len();
+        count.clear();
+        count.resize(len + 1, 0);
+        count[len] = 1;
+        for pos in (0..len).rev() {
+            let mut node = 0;
+            let mut total = 0;
+            for (end, &c) in design.iter().enumerate().skip(pos) {
+                let color = self.colors[c as usize];
+                if color == NO_COLOR {
+                    break;
+                }
+                node = self.children[node * self.num_colors + color];
+                if node == 0 {
+                    break;
+                }
+                if self.terminal[node] {
+                    total += count[end + 1];
+                }
+            }
+            count[pos] = total;
+        }
+        count[0]
+    }
+}
 
 fn dfs(line: &[u8], pos: usize, towels: &Vec<Box<[u8]>>, count: &mut Vec<i64>) {
     assert!(pos < line.len());
@@ -30,39 +135,104 @@ fn dfs(line: &[u8], pos: usize, towels: &Vec<Box<[u8]>>, count: &mut Vec<i64>) {
     count[pos] = total;
 }
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
-    let mut reader = BufReader::new(File::open(input).unwrap());
-
-    let mut towels_str = String::new();
-    reader.read_line(&mut towels_str).unwrap();
-    let mut towels: Vec<Box<[u8]>> = towels_str
-        .trim_end()
-        .split(", ")
-        .map(|s| s.bytes().collect())
-        .collect();
-    towels.sort(); // Use a radix tree?
+/// Counts the ways to arrange the design with `dfs` over the sorted towels.
+fn count_sorted(design: &[u8], towels: &Vec<Stripes>, count: &mut Vec<i64>) -> i64 {
+    let len = design.len();
+    count.resize(len + 1, 0);
+    count.fill(-1);
+    count[len] = 1;
+    if len > 0 {
+        dfs(design, 0, towels, count);
+    }
+    count[0]
+}
 
+/// Returns `(ans1, ans2)`.
+fn solve(designs: &[Stripes], towels: &Vec<Stripes>, matcher: Matcher) -> (i64, i64) {
+    let trie = match matcher {
+        Matcher::Trie => Some(Trie::new(towels)),
+        Matcher::Sorted => None,
+    };
     let mut ans1 = 0;
     let mut ans2 = 0;
     let mut count: Vec<i64> = Vec::new();
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.is_empty() {
-            continue;
-        }
-        let line = line.into_bytes().into_boxed_slice();
-        let len = line.len();
-        count.resize(len + 1, 0);
-        count.fill(-1);
-        count[len] = 1;
-        dfs(&line, 0, &towels, &mut count);
-        let total = count[0];
+    for design in designs {
+        let total = match &trie {
+            Some(trie) => trie.count(design, &mut count),
+            None => count_sorted(design, towels, &mut count),
+        };
         if total > 0 {
             ans1 += 1;
             ans2 += total;
         }
     }
+    (ans1, ans2)
+}
+
+/// Generates `num_towels` random towels of 1 to 8 stripes and designs of 30 to 40 stripes. A
+/// design of `n` stripes has at most `2^(n - 1)` arrangements, so the answers can't overflow.
+fn random_input(num_towels: usize) -> (Vec<Stripes>, Vec<Stripes>) {
+    // Seeded xorshift64: the towels and designs are the same on every run, so the timings of
+    // different builds can be compared.
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut random = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    };
+    // Random stripes, from `min_len` to `max_len` of them.
+    let mut stripes = |min_len: usize, max_len: usize| -> Stripes {
+        let len = min_len + random(max_len - min_len + 1);
+        (0..len)
+            .map(|_| BENCH_COLORS[random(BENCH_COLORS.len())])
+            .collect()
+    };
+    let mut towels: Vec<Stripes> = (0..num_towels).map(|_| stripes(1, 8)).collect();
+    towels.sort();
+    towels.dedup();
+    let designs: Vec<Stripes> = (0..BENCH_DESIGNS).map(|_| stripes(30, 40)).collect();
+    (towels, designs)
+}
+
+fn bench(num_towels: usize) {
+    let (towels, designs) = random_input(num_towels);
+    println!("{} towels, {} designs", towels.len(), designs.len());
+    let mut answers: Vec<(i64, i64)> = Vec::new();
+    for (name, matcher) in [("trie", Matcher::Trie), ("sorted", Matcher::Sorted)] {
+        let start = Instant::now();
+        answers.push(solve(&designs, &towels, matcher));
+        println!("{name}: {:.3}s", start.elapsed().as_secs_f64());
+    }
+    assert_eq!(answers[0], answers[1]);
+    println!("ans1 = {}, ans2 = {}", answers[0].0, answers[0].1);
+}
+
+fn main() {
+    let args = Cli::parse();
+    if let Some(num_towels) = args.bench {
+        bench(num_towels);
+        return;
+    }
+    let mut reader = BufReader::new(File::open(args.input.unwrap()).unwrap());
+
+    let mut towels_str = String::new();
+    reader.read_line(&mut towels_str).unwrap();
+    let mut towels: Vec<Stripes> = towels_str
+        .trim_end()
+        .split(", ")
+        .map(|s| s.bytes().collect())
+        .collect();
+    // `dfs` binary searches the sorted towels.
+    towels.sort();
+
+    let designs: Vec<Stripes> = reader
+        .lines()
+        .map(|line| line.unwrap())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.into_bytes().into_boxed_slice())
+        .collect();
+    let (ans1, ans2) = solve(&designs, &towels, args.matcher);
     println!("ans1 = {ans1}");
     println!("ans2 = {ans2}");
 }
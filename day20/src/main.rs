@@ -1,86 +1,295 @@
-use std::env;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::iter;
+use std::path::{Path, PathBuf};
 
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use clap::Parser;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
-    let reader = BufReader::new(File::open(input).unwrap());
-    let mut width = 0;
-    let mut walls: Vec<bool> = Vec::new();
-    let mut start = 0;
-    let mut end = 0;
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if width == 0 {
-            width = line.len() + 2;
-            walls.extend(iter::repeat_n(true, width));
+#[derive(Parser)]
+struct Cli {
+    input: PathBuf,
+    /// The maximum durations of cheats, one answer per duration.
+    #[arg(long, value_delimiter = ',', default_values_t = [2, 20])]
+    cheats: Vec<usize>,
+    /// Count only the cheats that save at least this many picoseconds.
+    #[arg(long, default_value_t = 100)]
+    min_saving: usize,
+    /// Print how many cheats save each number of picoseconds.
+    #[arg(long)]
+    histogram: bool,
+    /// List this many cheats of up to the longest duration that save the most time.
+    #[arg(long)]
+    list: Option<usize>,
+    /// Draw every listed cheat on the map, from `A` to `B`.
+    #[arg(long, requires = "list")]
+    render: bool,
+}
+
+/// A cheat from the track cell `start` to the track cell `end`.
+struct Cheat {
+    start: usize,
+    end: usize,
+    duration: usize,
+    saving: usize,
+}
+
+/// The race track, surrounded by walls, with the distances from the start and to the end.
+struct Racetrack {
+    width: usize,
+    height: usize,
+    walls: Vec<bool>,
+    start: usize,
+    end: usize,
+    from_start: Vec<usize>,
+    from_end: Vec<usize>,
+    best: usize,
+}
+
+impl Racetrack {
+    pub fn parse(path: &Path) -> Self {
+        let reader = BufReader::new(File::open(path).unwrap());
+        let mut width = 0;
+        let mut walls: Vec<bool> = Vec::new();
+        let mut start = 0;
+        let mut end = 0;
+        for line in reader.lines() {
+            let line = line.unwrap();
+            if width == 0 {
+                width = line.len() + 2;
+                walls.extend(iter::repeat_n(true, width));
+            }
+            walls.push(true);
+            for c in line.into_bytes() {
+                if c == b'S' {
+                    start = walls.len();
+                } else if c == b'E' {
+                    end = walls.len();
+                }
+                walls.push(c == b'#');
+            }
+            walls.push(true);
+        }
+        walls.extend(iter::repeat_n(true, width));
+        let height = walls.len() / width;
+
+        let from_start = bfs(&walls, width, start);
+        let from_end = bfs(&walls, width, end);
+        let best = from_start[end];
+        assert!(best != usize::MAX, "the end is unreachable");
+        Self {
+            width,
+            height,
+            walls,
+            start,
+            end,
+            from_start,
+            from_end,
+            best,
         }
-        walls.push(true);
-        for c in line.into_bytes() {
-            if c == b'S' {
-                start = walls.len();
-            } else if c == b'E' {
-                end = walls.len();
+    }
+
+    /// Returns the cells reachable from the start.
+    pub fn track(&self) -> Vec<usize> {
+        (0..self.walls.len())
+            .filter(|&index| self.from_start[index] != usize::MAX)
+            .collect()
+    }
+
+    /// Returns the cheats from `index` lasting at most `max_cheat` that save at least `min_saving`.
+    pub fn cheats(
+        &self,
+        index: usize,
+        max_cheat: usize,
+        min_saving: usize,
+    ) -> impl Iterator<Item = Cheat> + '_ {
+        let (x, y) = (index % self.width, index / self.width);
+        let ys = y.saturating_sub(max_cheat)..=(y + max_cheat).min(self.height - 1);
+        ys.flat_map(move |cheat_y| {
+            let dy = y.abs_diff(cheat_y);
+            let leftover = max_cheat - dy;
+            let xs = x.saturating_sub(leftover)..=(x + leftover).min(self.width - 1);
+            xs.filter_map(move |cheat_x| {
+                let cheat_index = cheat_y * self.width + cheat_x;
+                if self.from_end[cheat_index] == usize::MAX {
+                    return None;
+                }
+                let cheat_time = x.abs_diff(cheat_x) + dy;
+                let new_total = self.from_start[index] + cheat_time + self.from_end[cheat_index];
+                if new_total >= self.best || self.best - new_total < min_saving {
+                    return None;
+                }
+                Some(Cheat {
+                    start: index,
+                    end: cheat_index,
+                    duration: cheat_time,
+                    saving: self.best - new_total,
+                })
+            })
+        })
+    }
+
+    /// Converts an index to the coordinates on the map, without the surrounding walls.
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index % self.width - 1, index / self.width - 1)
+    }
+
+    pub fn render(&self, cheat: &Cheat) -> String {
+        let mut out = String::with_capacity(self.walls.len());
+        for y in 1..self.height - 1 {
+            for x in 1..self.width - 1 {
+                let index = y * self.width + x;
+                out.push(if index == cheat.start {
+                    'A'
+                } else if index == cheat.end {
+                    'B'
+                } else if index == self.start {
+                    'S'
+                } else if index == self.end {
+                    'E'
+                } else if self.walls[index] {
+                    '#'
+                } else {
+                    '.'
+                });
             }
-            walls.push(c == b'#');
+            out.push('\n');
         }
-        walls.push(true);
+        out
     }
-    walls.extend(iter::repeat_n(true, width));
-    let height = walls.len() / width;
-
-    let mut path: Vec<usize> = vec![start];
-    let mut current = path[0];
-    let mut prev = current;
-    while current != end {
-        for next in [current - width, current - 1, current + 1, current + width] {
-            if prev == next || walls[next] {
-                continue;
+}
+
+/// The cheats that save enough time, for every maximum duration. Every cheat is only counted for
+/// the shortest duration it fits into until `finish` sums them up.
+struct Tally {
+    counts: Vec<usize>,
+    /// The number of cheats by the time they save, if requested.
+    histograms: Option<Vec<Vec<usize>>>,
+}
+
+impl Tally {
+    pub fn new(tiers: usize, max_saving: usize, histogram: bool) -> Self {
+        Self {
+            counts: vec![0; tiers],
+            histograms: histogram.then(|| vec![vec![0; max_saving + 1]; tiers]),
+        }
+    }
+
+    pub fn add(&mut self, tier: usize, saving: usize) {
+        self.counts[tier] += 1;
+        if let Some(histograms) = &mut self.histograms {
+            histograms[tier][saving] += 1;
+        }
+    }
+
+    pub fn merge(mut self, other: Self) -> Self {
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts) {
+            *count += other_count;
+        }
+        if let (Some(histograms), Some(other_histograms)) = (&mut self.histograms, other.histograms)
+        {
+            for (histogram, other_histogram) in histograms.iter_mut().zip(other_histograms) {
+                for (count, other_count) in histogram.iter_mut().zip(other_histogram) {
+                    *count += other_count;
+                }
             }
-            prev = current;
-            current = next;
-            break;
         }
-        path.push(current);
+        self
     }
-    let total = path.len();
-    let mut dist: Vec<usize> = vec![usize::MAX; walls.len()];
-    for (i, &index) in path.iter().enumerate() {
-        dist[index] = total - i;
+
+    /// Counts every cheat for all the durations it fits into.
+    pub fn finish(mut self) -> Self {
+        for tier in 1..self.counts.len() {
+            self.counts[tier] += self.counts[tier - 1];
+            if let Some(histograms) = &mut self.histograms {
+                let (shorter, longer) = histograms.split_at_mut(tier);
+                for (count, shorter_count) in longer[0].iter_mut().zip(&shorter[tier - 1]) {
+                    *count += shorter_count;
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Returns the distances from `from` to every cell, `usize::MAX` for the unreachable ones.
+fn bfs(walls: &[bool], width: usize, from: usize) -> Vec<usize> {
+    let mut dist = vec![usize::MAX; walls.len()];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    dist[from] = 0;
+    queue.push_back(from);
+    while let Some(index) = queue.pop_front() {
+        for next in [index - width, index - 1, index + 1, index + width] {
+            if walls[next] || dist[next] != usize::MAX {
+                continue;
+            }
+            dist[next] = dist[index] + 1;
+            queue.push_back(next);
+        }
     }
+    dist
+}
+
+fn main() {
+    let args = Cli::parse();
+    let mut tiers = args.cheats;
+    tiers.sort_unstable();
+    tiers.dedup();
+    let max_cheat = *tiers.last().unwrap();
 
-    let (ans1, ans2) = path
+    let racetrack = Racetrack::parse(&args.input);
+    let track = racetrack.track();
+
+    let tally = track
         .par_iter()
-        .enumerate()
-        .map(|(i, &path_index)| {
-            let (x, y) = (path_index % width, path_index / width);
-            let mut res1 = 0;
-            let mut res2 = 0;
-            for cheat_y in y.saturating_sub(20)..=(y + 20).min(height - 1) {
-                let dy = y.abs_diff(cheat_y);
-                let leftover = 20 - dy;
-                for cheat_x in x.saturating_sub(leftover)..=(x + leftover).min(width - 1) {
-                    let cheat_index = cheat_y * width + cheat_x;
-                    if walls[cheat_index] {
-                        continue;
-                    }
-                    let dx = x.abs_diff(cheat_x);
-                    let cheat_time = dx + dy;
-                    let new_total = i + cheat_time + dist[cheat_index];
-                    if new_total < total && total - new_total >= 100 {
-                        if cheat_time <= 2 {
-                            res1 += 1;
-                        }
-                        res2 += 1;
-                    }
+        .fold(
+            || Tally::new(tiers.len(), racetrack.best, args.histogram),
+            |mut tally, &index| {
+                for cheat in racetrack.cheats(index, max_cheat, args.min_saving) {
+                    let tier = tiers.partition_point(|&tier| tier < cheat.duration);
+                    tally.add(tier, cheat.saving);
+                }
+                tally
+            },
+        )
+        .reduce(
+            || Tally::new(tiers.len(), racetrack.best, args.histogram),
+            Tally::merge,
+        )
+        .finish();
+
+    for (i, count) in tally.counts.iter().enumerate() {
+        println!("ans{} = {count}", i + 1);
+    }
+    if let Some(histograms) = &tally.histograms {
+        for (tier, histogram) in tiers.iter().zip(histograms) {
+            println!("cheats of up to {tier} picoseconds:");
+            for (saving, count) in histogram.iter().enumerate() {
+                if *count > 0 {
+                    println!("  {count} save {saving} picoseconds");
                 }
             }
-            (res1, res2)
-        })
-        .reduce(|| (0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
-    println!("ans1 = {ans1}");
-    println!("ans2 = {ans2}");
+        }
+    }
+
+    if let Some(n) = args.list {
+        let mut cheats: Vec<Cheat> = track
+            .par_iter()
+            .flat_map_iter(|&index| racetrack.cheats(index, max_cheat, args.min_saving))
+            .collect();
+        cheats.sort_unstable_by_key(|cheat| (Reverse(cheat.saving), cheat.start, cheat.end));
+        for cheat in cheats.iter().take(n) {
+            let (start_x, start_y) = racetrack.position(cheat.start);
+            let (end_x, end_y) = racetrack.position(cheat.end);
+            println!(
+                "({start_x}, {start_y}) -> ({end_x}, {end_y}) in {} picoseconds saves {}",
+                cheat.duration, cheat.saving
+            );
+            if args.render {
+                println!("{}", racetrack.render(cheat));
+            }
+        }
+    }
 }
@@ -1,200 +1,509 @@
-use std::env;
-use std::fs::File;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
-use std::mem;
-use std::sync::{LazyLock, Mutex};
+use std::path::PathBuf;
 
+use anyhow::{bail, ensure, Context};
+use clap::Parser;
 use hashbrown::HashMap;
+use num_bigint::BigUint;
 
-type Counter = HashMap<String, usize>;
-type Shift = (isize, isize);
-type Shifts = Vec<Shift>;
+// The counts grow exponentially with the number of robots and overflow `u128` at around 95.
+type Cost = BigUint;
+/// `costs[from][to]` is the number of button presses needed to move an arm from `from` to `to`
+/// and press `to`. Unreachable keys have no cost.
+type Costs = Vec<Vec<Option<Cost>>>;
 
-const PAD_WIDTH: usize = 3;
-const STEPS: i64 = 25;
+const PART1_DEPTH: usize = 2;
+const PART2_DEPTH: usize = 25;
+const GAP: u8 = b' ';
 
-static ROBOPAD: &[u8; 6] = b"X^A<v>";
-static KEYPAD: &[u8; 12] = b"789456123X0A";
-static ROBOPAD_INDICES: LazyLock<HashMap<u8, usize>> =
-    LazyLock::new(|| HashMap::from_iter(ROBOPAD.iter().copied().zip(0..)));
-static KEYPAD_INDICES: LazyLock<HashMap<u8, usize>> =
-    LazyLock::new(|| HashMap::from_iter(KEYPAD.iter().copied().zip(0..)));
+static DIRECTIONAL_PAD: &str = " ^A\n<v>";
+static NUMERIC_PAD: &str = "789\n456\n123\n 0A";
+static DIRECTIONS: [(u8, isize, isize); 4] =
+    [(b'^', 0, -1), (b'v', 0, 1), (b'<', -1, 0), (b'>', 1, 0)];
 
-fn to_coord(index: usize) -> (usize, usize) {
-    (index % PAD_WIDTH, index / PAD_WIDTH)
+#[derive(Parser)]
+struct Cli {
+    input: PathBuf,
+    /// File with additional keypad layouts. Each layout is a name line followed by the rows of
+    /// the pad, with an ASCII character per key and spaces for gaps. Layouts are separated by
+    /// empty lines. The built-in layouts are called `directional` and `numeric`.
+    #[arg(long)]
+    pads: Option<PathBuf>,
+    /// Comma-separated layouts of the keypads, from the one the human presses to the one
+    /// the code is typed on. Every robot operates the next pad using the previous one.
+    #[arg(long, value_delimiter = ',')]
+    chain: Vec<String>,
+    /// Print the answer for every number of robots on directional pads from 1 to this depth.
+    #[arg(long)]
+    depth: Option<usize>,
+    /// Print the sequence of presses of the human for every code.
+    #[arg(long)]
+    show: bool,
+    /// Longer sequences are printed run-length encoded and truncated to this many runs.
+    #[arg(long, default_value_t = 200)]
+    limit: usize,
 }
 
-fn parse_code(code: &str) -> Shifts {
-    let mut shifts: Shifts = Shifts::new();
-    let (mut prev_x, mut prev_y) = to_coord(KEYPAD_INDICES[&b'A']);
-    for c in code.bytes() {
-        let (x, y) = to_coord(KEYPAD_INDICES[&c]);
-        shifts.push((
-            (x as isize) - (prev_x as isize),
-            (y as isize) - (prev_y as isize),
-        ));
-        (prev_x, prev_y) = (x, y);
-    }
-    shifts
+struct Pad {
+    width: usize,
+    keys: Vec<u8>,
+    indices: HashMap<u8, usize>,
 }
 
-fn extract_shifts(inp: &str) -> Shifts {
-    let mut shifts: Shifts = Shifts::new();
-    let (mut dx, mut dy) = (0, 0);
-    for c in inp.bytes() {
-        match c {
-            b'^' => dy -= 1,
-            b'v' => dy += 1,
-            b'<' => dx -= 1,
-            b'>' => dx += 1,
-            b'A' => {
-                shifts.push((dx, dy));
-                (dx, dy) = (0, 0);
+impl Pad {
+    /// Parses a pad from its rows. Rows may have different lengths, missing cells are gaps.
+    pub fn parse(layout: &str) -> anyhow::Result<Self> {
+        let rows: Vec<&[u8]> = layout.lines().map(str::as_bytes).collect();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut keys: Vec<u8> = Vec::with_capacity(width * rows.len());
+        for row in rows {
+            keys.extend(row);
+            keys.resize(keys.len() + width - row.len(), GAP);
+        }
+        let mut indices: HashMap<u8, usize> = HashMap::new();
+        for (index, &key) in keys.iter().enumerate() {
+            ensure!(key.is_ascii(), "non-ASCII key in {layout:?}");
+            if key != GAP && indices.insert(key, index).is_some() {
+                bail!("duplicate key {:?}", key as char);
             }
-            _ => panic!("unknown shift"),
         }
+        ensure!(indices.contains_key(&b'A'), "no `A` key to start from");
+        Ok(Self {
+            width,
+            keys,
+            indices,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn index(&self, key: u8) -> Option<usize> {
+        self.indices.get(&key).copied()
+    }
+
+    /// Moves the arm from `index` by `(dx, dy)`, returning `None` if it leaves the pad or hits
+    /// a gap.
+    pub fn step(&self, index: usize, (dx, dy): (isize, isize)) -> Option<usize> {
+        let x = (index % self.width).checked_add_signed(dx)?;
+        let y = (index / self.width).checked_add_signed(dy)?;
+        let next = y * self.width + x;
+        (x < self.width && next < self.len() && self.keys[next] != GAP).then_some(next)
     }
-    shifts
 }
 
-fn process_command(is_keypad: bool, cur: usize, (dx, dy): Shift) -> (String, usize) {
-    use std::cmp::Ordering::*;
-    type CommandsCacheState = (bool, usize, Shift);
-    static COMMANDS_CACHE: LazyLock<Mutex<HashMap<CommandsCacheState, String>>> =
-        LazyLock::new(|| Mutex::new(HashMap::new()));
-
-    let nxt = cur.wrapping_add_signed(dy * (PAD_WIDTH as isize) + dx);
-    let cmd = COMMANDS_CACHE
-        .lock()
-        .unwrap()
-        .entry((is_keypad, cur, (dx, dy)))
-        .or_insert_with(|| match (dx.cmp(&0), dy.cmp(&0)) {
-            (Less, Less) => {
-                if is_keypad
-                    && ((KEYPAD[cur] == b'A' && dx == -2) || (KEYPAD[cur] == b'0' && dx == -1))
-                {
-                    format!(
-                        "<{}v<{}>>^A",
-                        "A".repeat(dy.unsigned_abs()),
-                        "A".repeat(dx.unsigned_abs())
-                    )
-                } else {
-                    format!(
-                        "v<<{}>^{}>A",
-                        "A".repeat(dx.unsigned_abs()),
-                        "A".repeat(dy.unsigned_abs())
-                    )
-                }
+/// Parses the named layouts of a description file.
+fn parse_pads(description: &str) -> anyhow::Result<HashMap<String, Pad>> {
+    let mut pads: HashMap<String, Pad> = HashMap::new();
+    let description = description.replace("\r\n", "\n");
+    for block in description.split("\n\n") {
+        let block = block.trim_matches('\n');
+        if block.is_empty() {
+            continue;
+        }
+        let Some((name, layout)) = block.split_once('\n') else {
+            bail!("layout without rows: {block:?}");
+        };
+        let pad = Pad::parse(layout).with_context(|| format!("invalid layout {name:?}"))?;
+        pads.insert(name.trim().to_string(), pad);
+    }
+    Ok(pads)
+}
+
+/// Looks up the pads of a chain, checking that every pad operating a robot has the direction
+/// keys.
+fn resolve_chain<'a, S: AsRef<str>>(
+    pads: &'a HashMap<String, Pad>,
+    names: &[S],
+) -> anyhow::Result<Vec<&'a Pad>> {
+    ensure!(!names.is_empty(), "empty chain");
+    let mut chain: Vec<&Pad> = Vec::with_capacity(names.len());
+    for (i, name) in names.iter().enumerate() {
+        let name = name.as_ref();
+        let pad = pads
+            .get(name)
+            .with_context(|| format!("unknown pad {name:?}"))?;
+        if i + 1 < names.len() {
+            for (key, ..) in DIRECTIONS {
+                ensure!(
+                    pad.index(key).is_some(),
+                    "pad {name:?} controls a robot but has no {:?} key",
+                    key as char
+                );
             }
-            (Less, Equal) => {
-                format!("<v<{}>>^A", "A".repeat(dx.unsigned_abs()))
+        }
+        chain.push(pad);
+    }
+    Ok(chain)
+}
+
+/// The presses needed to operate an arm on a pad.
+struct Layer {
+    costs: Costs,
+    /// `moves[from][to]` are the controller keys pressed by an optimal sequence for
+    /// `costs[from][to]`, ending with `A`. Empty for the pad the human presses.
+    moves: Vec<Vec<Vec<usize>>>,
+}
+
+impl Layer {
+    /// The human presses every key directly.
+    pub fn direct(pad: &Pad) -> Self {
+        Self {
+            costs: vec![vec![Some(Cost::from(1u8)); pad.len()]; pad.len()],
+            moves: vec![vec![Vec::new(); pad.len()]; pad.len()],
+        }
+    }
+
+    /// Computes the layer for an arm on `pad` operated through `controller`, given the layer of
+    /// `controller`.
+    ///
+    /// This is a shortest path search over pairs `(arm position, controller arm position)`. The
+    /// controller arm starts and ends at `A`, and every other press on it moves the arm on
+    /// `pad`. Paths through the gap are never generated, so there is no need to special-case the
+    /// move orderings.
+    pub fn controlled(pad: &Pad, controller: &Pad, controller_layer: &Layer) -> Self {
+        let controller_costs = &controller_layer.costs;
+        let activate = controller.index(b'A').unwrap();
+        let directions: Vec<(usize, (isize, isize))> = DIRECTIONS
+            .iter()
+            .map(|&(key, dx, dy)| (controller.index(key).unwrap(), (dx, dy)))
+            .collect();
+        let mut costs: Costs = vec![vec![None; pad.len()]; pad.len()];
+        let mut moves = vec![vec![Vec::new(); pad.len()]; pad.len()];
+        let mut dist: Vec<Option<Cost>> = vec![None; pad.len() * controller.len()];
+        let mut prev = vec![usize::MAX; pad.len() * controller.len()];
+        let mut best = vec![usize::MAX; pad.len()];
+        let mut queue: BinaryHeap<Reverse<(Cost, usize)>> = BinaryHeap::new();
+        for &from in pad.indices.values() {
+            dist.fill(None);
+            best.fill(usize::MAX);
+            let start_index = from * controller.len() + activate;
+            dist[start_index] = Some(Cost::ZERO);
+            queue.push(Reverse((Cost::ZERO, start_index)));
+            while let Some(Reverse((prio, index))) = queue.pop() {
+                if dist[index].as_ref().is_some_and(|d| prio > *d) {
+                    continue;
+                }
+                let (current, current_controller) =
+                    (index / controller.len(), index % controller.len());
+                if let Some(press_cost) = &controller_costs[current_controller][activate] {
+                    let pressed = &prio + press_cost;
+                    if costs[from][current].as_ref().is_none_or(|c| pressed < *c) {
+                        costs[from][current] = Some(pressed);
+                        best[current] = index;
+                    }
+                }
+                for &(direction, shift) in &directions {
+                    let Some(next) = pad.step(current, shift) else {
+                        continue;
+                    };
+                    let Some(move_cost) = &controller_costs[current_controller][direction] else {
+                        continue;
+                    };
+                    let next_index = next * controller.len() + direction;
+                    let alt = &prio + move_cost;
+                    if dist[next_index].as_ref().is_none_or(|d| alt < *d) {
+                        dist[next_index] = Some(alt.clone());
+                        prev[next_index] = index;
+                        queue.push(Reverse((alt, next_index)));
+                    }
+                }
             }
-            (Less, Greater) => {
-                if !is_keypad
-                    && ((ROBOPAD[cur] == b'A' && dx == -2) || (ROBOPAD[cur] == b'^' && dx == -1))
-                {
-                    format!(
-                        "<v{}<{}>>^A",
-                        "A".repeat(dy.unsigned_abs()),
-                        "A".repeat(dx.unsigned_abs())
-                    )
-                } else {
-                    format!(
-                        "v<<{}>{}^>A",
-                        "A".repeat(dx.unsigned_abs()),
-                        "A".repeat(dy.unsigned_abs())
-                    )
+            for (to, &end_index) in best.iter().enumerate() {
+                if end_index == usize::MAX {
+                    continue;
                 }
+                // The controller position of every state is the key that was pressed last.
+                let mut index = end_index;
+                let mut path: Vec<usize> = vec![activate];
+                while index != start_index {
+                    path.push(index % controller.len());
+                    index = prev[index];
+                }
+                path.reverse();
+                moves[from][to] = path;
             }
-            (Equal, Less) => {
-                format!("<{}>A", "A".repeat(dy.unsigned_abs()))
+        }
+        Self { costs, moves }
+    }
+}
+
+/// A chain of pads, from the one the human presses to the one the code is typed on.
+struct Chain<'a> {
+    pads: Vec<&'a Pad>,
+    layers: Vec<Layer>,
+}
+
+impl<'a> Chain<'a> {
+    pub fn new(pads: Vec<&'a Pad>) -> Self {
+        let mut chain = Self {
+            pads: vec![pads[0]],
+            layers: vec![Layer::direct(pads[0])],
+        };
+        for pad in &pads[1..] {
+            chain.push(pad);
+        }
+        chain
+    }
+
+    /// Adds a pad operated by a robot using the current last pad.
+    pub fn push(&mut self, pad: &'a Pad) {
+        let controller = self.keypad();
+        let layer = Layer::controlled(pad, controller, &self.layers[self.layers.len() - 1]);
+        self.pads.push(pad);
+        self.layers.push(layer);
+    }
+
+    pub fn pop(&mut self) {
+        assert!(self.pads.len() > 1, "the human pad cannot be removed");
+        self.pads.pop();
+        self.layers.pop();
+    }
+
+    fn keypad(&self) -> &'a Pad {
+        self.pads[self.pads.len() - 1]
+    }
+
+    /// Converts the code into key indices of the last pad, starting with `A`.
+    fn code_indices(&self, code: &str) -> anyhow::Result<Vec<usize>> {
+        let keypad = self.keypad();
+        let costs = &self.layers[self.layers.len() - 1].costs;
+        let mut indices: Vec<usize> = vec![keypad.index(b'A').unwrap()];
+        for c in code.bytes() {
+            let Some(nxt) = keypad.index(c) else {
+                bail!("no key {:?} for code {code:?}", c as char);
+            };
+            ensure!(
+                costs[indices[indices.len() - 1]][nxt].is_some(),
+                "key {:?} is unreachable for code {code:?}",
+                c as char
+            );
+            indices.push(nxt);
+        }
+        Ok(indices)
+    }
+
+    /// The length of the shortest sequence the human has to press to type the code.
+    pub fn cost(&self, code: &str) -> anyhow::Result<Cost> {
+        let costs = &self.layers[self.layers.len() - 1].costs;
+        let indices = self.code_indices(code)?;
+        Ok(indices
+            .windows(2)
+            .map(|w| costs[w[0]][w[1]].as_ref().unwrap())
+            .sum())
+    }
+
+    /// The sum of the complexities of the codes.
+    pub fn complexity(&self, codes: &[String]) -> anyhow::Result<Cost> {
+        let mut total = Cost::ZERO;
+        for code in codes {
+            total += self.cost(code)? * code_number(code);
+        }
+        Ok(total)
+    }
+
+    /// Generates the shortest sequence the human has to press to type the code, passing every
+    /// key to `emit`. Stops early if `emit` returns `false`.
+    pub fn expand<F: FnMut(u8) -> bool>(&self, code: &str, mut emit: F) -> anyhow::Result<()> {
+        let indices = self.code_indices(code)?;
+        let level = self.layers.len() - 1;
+        for w in indices.windows(2) {
+            if !self.expand_move(level, w[0], w[1], &mut emit) {
+                break;
             }
-            (Equal, Equal) => "A".to_string(),
-            (Equal, Greater) => {
-                format!("v<{}^>A", "A".repeat(dy.unsigned_abs()))
+        }
+        Ok(())
+    }
+
+    fn expand_move<F: FnMut(u8) -> bool>(
+        &self,
+        level: usize,
+        from: usize,
+        to: usize,
+        emit: &mut F,
+    ) -> bool {
+        if level == 0 {
+            return emit(self.pads[0].keys[to]);
+        }
+        let controller = self.pads[level - 1];
+        let mut cur = controller.index(b'A').unwrap();
+        for &nxt in &self.layers[level].moves[from][to] {
+            if !self.expand_move(level - 1, cur, nxt, emit) {
+                return false;
             }
-            (Greater, Less) => {
-                if !is_keypad
-                    && ((ROBOPAD[nxt] == b'A' && dx == 2) || (ROBOPAD[nxt] == b'^' && dx == 1))
-                {
-                    format!(
-                        "v{}<^{}>A",
-                        "A".repeat(dx.unsigned_abs()),
-                        "A".repeat(dy.unsigned_abs())
-                    )
-                } else {
-                    format!(
-                        "<{}>v{}^A",
-                        "A".repeat(dy.unsigned_abs()),
-                        "A".repeat(dx.unsigned_abs())
-                    )
+            cur = nxt;
+        }
+        true
+    }
+
+    /// Replays the presses of the human through the chain, returning the typed keys. Fails if
+    /// an arm ends up over a gap or a key that does nothing is pressed.
+    pub fn replay(&self, presses: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut arms: Vec<usize> = self
+            .pads
+            .iter()
+            .map(|pad| pad.index(b'A').unwrap())
+            .collect();
+        let mut typed: Vec<u8> = Vec::new();
+        for (i, &press) in presses.iter().enumerate() {
+            // The key pressed on the current pad, if any.
+            let mut key = Some(press);
+            for (level, (pad, arm)) in self.pads.iter().zip(arms.iter_mut()).enumerate().skip(1) {
+                let Some(pressed) = key else {
+                    break;
+                };
+                if pressed == b'A' {
+                    key = Some(pad.keys[*arm]);
+                    continue;
                 }
+                let Some(&(_, dx, dy)) = DIRECTIONS.iter().find(|(dir, ..)| *dir == pressed) else {
+                    bail!("press {i}: key {:?} does nothing", pressed as char);
+                };
+                let Some(next) = pad.step(*arm, (dx, dy)) else {
+                    bail!("press {i}: the arm on pad {level} panics");
+                };
+                *arm = next;
+                key = None;
             }
-            (Greater, Equal) => {
-                format!("v{}^A", "A".repeat(dx.unsigned_abs()))
-            }
-            (Greater, Greater) => {
-                if is_keypad
-                    && ((KEYPAD[nxt] == b'A' && dx == 2) || (KEYPAD[nxt] == b'0' && dx == 1))
-                {
-                    format!(
-                        "v{}<{}^>A",
-                        "A".repeat(dx.unsigned_abs()),
-                        "A".repeat(dy.unsigned_abs())
-                    )
-                } else {
-                    format!(
-                        "v<{}>{}^A",
-                        "A".repeat(dy.unsigned_abs()),
-                        "A".repeat(dx.unsigned_abs())
-                    )
+            typed.extend(key);
+        }
+        Ok(typed)
+    }
+}
+
+/// Run-length encodes a sequence of keys, e.g. `v<<A>>^A` becomes `v<×2A>×2^A`. The keys are
+/// ASCII, so the `×` can't be mistaken for one, even on pads with digit keys. Stops after `limit`
+/// runs and marks the output as truncated.
+struct RunLength {
+    out: String,
+    runs: usize,
+    limit: usize,
+    last: Option<(u8, usize)>,
+}
+
+impl RunLength {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            out: String::new(),
+            runs: 0,
+            limit,
+            last: None,
+        }
+    }
+
+    /// Appends a key, returning `false` once the limit is reached.
+    pub fn push(&mut self, key: u8) -> bool {
+        match &mut self.last {
+            Some((last_key, count)) if *last_key == key => *count += 1,
+            _ => {
+                self.flush();
+                if self.runs == self.limit {
+                    self.out.push_str("...");
+                    return false;
                 }
+                self.last = Some((key, 1));
             }
-        })
-        .clone();
-    (cmd, nxt)
+        }
+        true
+    }
+
+    pub fn finish(mut self) -> String {
+        self.flush();
+        self.out
+    }
+
+    fn flush(&mut self) {
+        if let Some((key, count)) = self.last.take() {
+            self.out.push(key as char);
+            if count > 1 {
+                self.out.push('×');
+                self.out.push_str(&count.to_string());
+            }
+            self.runs += 1;
+        }
+    }
 }
 
-fn simulate(inp: &str, is_keypad: bool, mult: usize, cmds: &mut Counter) {
-    let (mut cur, shifts) = if is_keypad {
-        (KEYPAD_INDICES[&b'A'], parse_code(inp))
-    } else {
-        (ROBOPAD_INDICES[&b'A'], extract_shifts(inp))
-    };
-    for shift in shifts {
-        let (cmd, nxt) = process_command(is_keypad, cur, shift);
-        *cmds.entry(cmd).or_default() += mult;
-        cur = nxt;
+/// Prints the sequence of the human for every code, verifying it by replaying it through the
+/// chain. Sequences longer than `limit` are run-length encoded and truncated.
+fn show(chain: &Chain, codes: &[String], limit: usize) -> anyhow::Result<()> {
+    for code in codes {
+        let cost = chain.cost(code)?;
+        if let Some(cost) = usize::try_from(&cost).ok().filter(|&cost| cost <= limit) {
+            let mut presses: Vec<u8> = Vec::with_capacity(cost);
+            chain.expand(code, |key| {
+                presses.push(key);
+                true
+            })?;
+            let typed = chain.replay(&presses)?;
+            ensure!(
+                typed == code.as_bytes(),
+                "replaying the sequence for {code:?} types {:?}",
+                String::from_utf8_lossy(&typed)
+            );
+            println!("{code}: {}", String::from_utf8_lossy(&presses));
+        } else {
+            let mut encoded = RunLength::new(limit);
+            chain.expand(code, |key| encoded.push(key))?;
+            println!("{code}: {} ({cost} presses)", encoded.finish());
+        }
     }
+    Ok(())
 }
 
-fn compute_weight(counter: &Counter) -> usize {
-    counter.iter().map(|(cmd, count)| cmd.len() * count).sum()
+/// The numeric part of the code, ignoring any other keys.
+fn code_number(code: &str) -> usize {
+    code.bytes()
+        .filter(u8::is_ascii_digit)
+        .fold(0, |acc, digit| acc * 10 + (digit - b'0') as usize)
 }
 
-fn main() {
-    let input = env::args_os().nth(1).unwrap();
-    let reader = BufReader::new(File::open(input).unwrap());
-    let mut ans1 = 0;
-    let mut ans2 = 0;
-    let mut cmds = Counter::new();
-    let mut new_cmds = Counter::new();
-    for line in reader.lines() {
-        let line = line.unwrap();
-        let number: usize = line[..line.len() - 1].parse().unwrap();
-        cmds.clear();
-        simulate(&line, true, 1, &mut cmds);
-        for step in 2..=STEPS {
-            for (cmd, count) in cmds.drain() {
-                simulate(&cmd, false, count, &mut new_cmds);
-            }
-            mem::swap(&mut cmds, &mut new_cmds);
-            if step == 2 {
-                ans1 += number * compute_weight(&cmds);
-            }
+fn main() -> anyhow::Result<()> {
+    let args = Cli::parse();
+    let reader = BufReader::new(File::open(&args.input)?);
+    let codes: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+
+    let mut pads: HashMap<String, Pad> = HashMap::new();
+    pads.insert("directional".to_string(), Pad::parse(DIRECTIONAL_PAD)?);
+    pads.insert("numeric".to_string(), Pad::parse(NUMERIC_PAD)?);
+    if let Some(path) = &args.pads {
+        let description = fs::read_to_string(path)?;
+        pads.extend(parse_pads(&description).with_context(|| format!("cannot parse {path:?}"))?);
+    }
+
+    if !args.chain.is_empty() {
+        let chain = Chain::new(resolve_chain(&pads, &args.chain)?);
+        println!("ans = {}", chain.complexity(&codes)?);
+        if args.show {
+            show(&chain, &codes, args.limit)?;
         }
-        ans2 += number * compute_weight(&cmds);
+        return Ok(());
     }
-    println!("ans1 = {ans1}");
-    println!("ans2 = {ans2}");
+
+    let [robopad, keypad] = resolve_chain(&pads, &["directional", "numeric"])?[..] else {
+        unreachable!();
+    };
+    let max_depth = args.depth.unwrap_or(PART2_DEPTH);
+    let mut chain = Chain::new(vec![robopad]);
+    for depth in 1..=max_depth {
+        chain.push(robopad);
+        chain.push(keypad);
+        let ans = chain.complexity(&codes)?;
+        match args.depth {
+            Some(_) => println!("depth {depth}: {ans}"),
+            None if depth == PART1_DEPTH => println!("ans1 = {ans}"),
+            None if depth == PART2_DEPTH => println!("ans2 = {ans}"),
+            None => {}
+        }
+        if args.show && (args.depth.is_some() || [PART1_DEPTH, PART2_DEPTH].contains(&depth)) {
+            show(&chain, &codes, args.limit)?;
+        }
+        chain.pop();
+    }
+
+    Ok(())
 }